@@ -1,10 +1,91 @@
 use zellij_tile::prelude::*;
 use zj_smart_sessions::{
-    new_session_list::{query_list, NewSessionList},
+    git, help, hints,
+    history::History,
+    listing,
+    metadata::Metadata,
+    new_session_list::{DirectorySource, NewSessionList},
+    pipe::Command,
+    preview,
     session_list::SessionList,
+    sort::SortOrder,
 };
 
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, time::Duration};
+
+/// Text input, that temporarily replaces the search query.
+struct Prompt {
+    label: &'static str,
+    input: String,
+    action: PromptAction,
+}
+
+enum PromptAction {
+    Worktree,
+    Tags,
+}
+
+/// What the plugin does, when it is opened.
+#[derive(Default, PartialEq)]
+enum Mode {
+    /// Show the session or directory list.
+    #[default]
+    List,
+    /// Attach to the previously used session without showing a list.
+    Toggle,
+}
+
+impl Mode {
+    fn from_config(config: &BTreeMap<String, String>) -> Self {
+        match config.get("mode").map(|m| m.as_str()) {
+            Some("toggle") => Self::Toggle,
+            _ => Self::List,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::List => "list",
+            Self::Toggle => "toggle",
+        }
+    }
+}
+
+/// Progress or error of a background action, shown above the footer.
+enum Status {
+    Progress(String),
+    Error(String),
+}
+
+/// Line of the first list row, below the search header.
+const LIST_Y: usize = 2;
+
+/// Width of the list. With enough columns, the right half shows a preview
+/// of the selected item.
+fn list_columns(cols: usize) -> usize {
+    match cols >= preview::MIN_COLUMNS {
+        true => cols / 2,
+        false => cols,
+    }
+}
+
+/// Columns and rows of the preview.
+fn preview_size(rows: usize, cols: usize) -> (usize, usize) {
+    (cols - list_columns(cols) - 1, rows - 5)
+}
+
+fn render_preview(lines: Vec<Text>, rows: usize, cols: usize) {
+    let x = list_columns(cols) + 1;
+    let (width, height) = preview_size(rows, cols);
+
+    for (y, line) in lines.into_iter().take(height).enumerate() {
+        print_text_with_coordinates(line, x, y + LIST_Y, Some(width), Some(1));
+    }
+}
+
+fn context_value<'a>(ctx: &'a BTreeMap<String, String>, key: &str) -> &'a str {
+    ctx.get(key).map(|v| v.as_str()).unwrap_or_default()
+}
 
 #[cfg(not(test))]
 register_plugin!(State);
@@ -33,8 +114,409 @@ struct State {
     session_list: SessionList,
     new_session_list: NewSessionList,
     search_query: String,
-    find_command: Option<String>,
+    source: Option<DirectorySource>,
     queried_files: bool,
+    prompt: Option<Prompt>,
+    status: Option<Status>,
+    awaiting_hint: bool,
+    /// Scroll offset of the help view, while it is shown.
+    help: Option<usize>,
+    /// Width of the rendered list. The preview is shown right of it.
+    list_cols: usize,
+    mode: Mode,
+    toggled: bool,
+    has_sessions: bool,
+    resurrectable_sessions: Vec<(String, Duration)>,
+    /// Piped commands, that wait for the first `SessionUpdate`.
+    pending_commands: Vec<(Command, Option<String>)>,
+}
+
+impl State {
+    fn handle_prompt_key(&mut self, key: KeyWithModifier) {
+        let prompt = match self.prompt.as_mut() {
+            Some(prompt) => prompt,
+            None => return,
+        };
+
+        match key.bare_key {
+            BareKey::Enter => {
+                let input = prompt.input.trim().to_owned();
+                let action = match self.prompt.take() {
+                    Some(prompt) => prompt.action,
+                    None => return,
+                };
+
+                match action {
+                    PromptAction::Worktree => self.add_worktree(&input),
+                    PromptAction::Tags => self.session_list.set_selected_tags(&input),
+                }
+            }
+            BareKey::Esc => {
+                self.prompt = None;
+            }
+            BareKey::Backspace => {
+                prompt.input.pop();
+            }
+            BareKey::Char(c) if key.has_no_modifiers() => {
+                prompt.input.push(c);
+            }
+            _ => {}
+        }
+    }
+
+    /// Configuration values in effect for the active list.
+    fn settings(&self) -> Vec<(&'static str, String)> {
+        let mut settings = vec![("mode", self.mode.label().to_owned())];
+        settings.extend(match self.source {
+            Some(_) => self.new_session_list.settings(),
+            None => self.session_list.settings(),
+        });
+
+        settings
+    }
+
+    /// Close the help view with `?` or `Esc` and scroll it with the arrows.
+    fn handle_help_key(&mut self, key: KeyWithModifier) {
+        let len = help::lines(self.source.is_some(), &self.settings()).len();
+        let offset = match self.help.as_mut() {
+            Some(offset) => offset,
+            None => return,
+        };
+
+        match key.bare_key {
+            BareKey::Char('?') | BareKey::Esc => self.help = None,
+            BareKey::Down => *offset = (*offset + 1).min(len.saturating_sub(1)),
+            BareKey::Up => *offset = offset.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    /// Record the current session and, in toggle mode, attach to the
+    /// previous one.
+    fn handle_sessions(&mut self, sessions: &[SessionInfo]) {
+        let mut history = History::load();
+        if let Some(current) = sessions.iter().find(|s| s.is_current_session) {
+            history.visit(&current.name);
+        }
+
+        if self.mode != Mode::Toggle || self.toggled {
+            return;
+        }
+        self.toggled = true;
+
+        match history.previous(sessions).cloned() {
+            Some(previous) => {
+                history.visit(&previous);
+                switch_session_with_focus(&previous, None, None);
+                close_self();
+            }
+            None => {
+                self.status = Some(Status::Error("No previous session".to_owned()));
+            }
+        }
+    }
+
+    /// Run a command from a pipe. Output for `zellij pipe` is written to its
+    /// stdout.
+    fn run_pipe_command(&mut self, command: Command, pipe_id: Option<String>) {
+        let output = match command {
+            Command::Attach(name) => {
+                let result = self.new_session_list.attach(&name);
+                self.report(result)
+            }
+            Command::Create(path) => {
+                let result = self.new_session_list.create_or_attach_path(&path);
+                self.report(result)
+            }
+            Command::List => listing::to_json(
+                self.session_list.sessions(),
+                &self.resurrectable_sessions,
+                self.new_session_list.frecency(),
+            ),
+        };
+
+        if let Some(pipe_id) = pipe_id {
+            if !output.is_empty() {
+                cli_pipe_output(&pipe_id, &format!("{output}\n"));
+            }
+            unblock_cli_pipe_input(&pipe_id);
+        }
+    }
+
+    /// Show the progress or error of an action as status and return it.
+    fn report(&mut self, result: Result<Option<String>, String>) -> String {
+        match result {
+            Ok(Some(progress)) => {
+                self.status = Some(Status::Progress(progress.clone()));
+                progress
+            }
+            Ok(None) => "".to_owned(),
+            Err(e) => {
+                self.status = Some(Status::Error(e.clone()));
+                e
+            }
+        }
+    }
+
+    /// Open the session or directory with the given hint.
+    fn jump(&mut self, hint: char) {
+        let index = match hints::index(hint) {
+            Some(index) => index,
+            None => return,
+        };
+
+        if self.source.is_some() {
+            let result = self.new_session_list.attach_index(index);
+            self.show_opened(result);
+        } else if self.session_list.attach_index(index) {
+            close_self();
+        }
+    }
+
+    fn add_worktree(&mut self, branch: &str) {
+        if branch.is_empty() {
+            return;
+        }
+
+        self.status = match self.new_session_list.add_worktree(branch) {
+            Ok(path) => Some(Status::Progress(format!(
+                "Creating worktree for {branch} in {path}..."
+            ))),
+            Err(e) => Some(Status::Error(e)),
+        };
+    }
+
+    fn handle_worktree_added(
+        &mut self,
+        code: Option<i32>,
+        stderr: &str,
+        ctx: &BTreeMap<String, String>,
+    ) {
+        let get = |key: &str| context_value(ctx, key);
+
+        if code == Some(0) {
+            self.open_session(Some(get("name")), get("path"));
+
+            return;
+        }
+
+        // The branch does not exist yet, so retry by creating it.
+        if get("create_branch") == "false" && stderr.contains("invalid reference") {
+            git::add_worktree(get("repo"), get("branch"), get("path"), get("name"), true);
+
+            return;
+        }
+
+        self.status = Some(Status::Error(format!(
+            "Creating worktree failed: {}",
+            stderr.trim()
+        )));
+    }
+
+    /// Open the session, once the directory for it exists. The plugin stays
+    /// open, while the manifest is read.
+    fn open_session(&mut self, name: Option<&str>, cwd: &str) {
+        let result = self.new_session_list.open(name, cwd);
+        self.show_opened(result);
+    }
+
+    /// Close the plugin after a session was opened. It stays open with the
+    /// progress, while the session is not opened yet, or with an error.
+    fn show_opened(&mut self, result: Result<Option<String>, String>) {
+        match result {
+            Ok(Some(progress)) => self.status = Some(Status::Progress(progress)),
+            Ok(None) => close_self(),
+            Err(e) => self.status = Some(Status::Error(e)),
+        }
+    }
+
+    fn handle_manifest(&mut self, code: Option<i32>, stdout: &str, ctx: &BTreeMap<String, String>) {
+        let get = |key: &str| context_value(ctx, key);
+
+        if let Err(e) = self
+            .new_session_list
+            .update_manifest(get("path"), code, stdout)
+        {
+            self.status = Some(Status::Error(e));
+
+            return;
+        }
+
+        if get("open") == "true" {
+            let name = Some(get("name")).filter(|name| !name.is_empty());
+            self.open_session(name, get("path"));
+        }
+    }
+
+    fn handle_cloned(&mut self, code: Option<i32>, stderr: &str, ctx: &BTreeMap<String, String>) {
+        let get = |key: &str| context_value(ctx, key);
+
+        if code != Some(0) {
+            self.status = Some(Status::Error(format!("Cloning failed: {}", stderr.trim())));
+
+            return;
+        }
+
+        self.new_session_list.add_entry(get("path"));
+
+        self.open_session(None, get("path"));
+    }
+
+    fn handle_directory_created(
+        &mut self,
+        code: Option<i32>,
+        stderr: &str,
+        ctx: &BTreeMap<String, String>,
+    ) {
+        let get = |key: &str| context_value(ctx, key);
+
+        if code != Some(0) {
+            self.status = Some(Status::Error(format!(
+                "Creating directory failed: {}",
+                stderr.trim()
+            )));
+
+            return;
+        }
+
+        self.new_session_list.add_entry(get("path"));
+
+        if self.new_session_list.init_scratch(get("path"), get("name")) {
+            self.status = Some(Status::Progress(format!(
+                "Running init hook in {}...",
+                get("path")
+            )));
+
+            return;
+        }
+
+        self.open_session(None, get("path"));
+    }
+
+    fn handle_scratch_initialized(
+        &mut self,
+        code: Option<i32>,
+        stderr: &str,
+        ctx: &BTreeMap<String, String>,
+    ) {
+        let get = |key: &str| context_value(ctx, key);
+
+        if code != Some(0) {
+            self.status = Some(Status::Error(format!(
+                "Init hook failed: {}",
+                stderr.trim()
+            )));
+
+            return;
+        }
+
+        self.open_session(None, get("path"));
+    }
+
+    /// Move the selection with the wheel and select or expand rows with
+    /// clicks. A double click attaches.
+    fn handle_mouse(&mut self, mouse: Mouse) -> bool {
+        let is_new_session = self.source.is_some();
+
+        match mouse {
+            Mouse::ScrollUp(_) if is_new_session => self.new_session_list.select_prev(),
+            Mouse::ScrollDown(_) if is_new_session => self.new_session_list.select_next(),
+            Mouse::ScrollUp(_) => self.session_list.select_prev(),
+            Mouse::ScrollDown(_) => self.session_list.select_next(),
+            Mouse::LeftClick(line, column) => {
+                // Clicks above the list or in the preview are ignored.
+                if line < LIST_Y as isize || column >= self.list_cols {
+                    return false;
+                }
+                let row = line as usize - LIST_Y;
+
+                if is_new_session {
+                    if self.new_session_list.click(row) {
+                        let result = self.new_session_list.create_or_attach();
+                        self.show_opened(result);
+                    }
+
+                    return true;
+                }
+
+                if self.session_list.click(row, column) {
+                    if self.session_list.toggle_selected_group() {
+                        return true;
+                    }
+
+                    self.session_list.attach_selected();
+                    close_self();
+                }
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Filter the list with the changed search query.
+    fn filter(&mut self) {
+        match self.source {
+            Some(_) => self.new_session_list.filter(&self.search_query),
+            None => self.session_list.filter(&self.search_query),
+        }
+    }
+
+    fn sort_order(&self) -> SortOrder {
+        match self.source {
+            Some(_) => self.new_session_list.sort_order(),
+            None => self.session_list.sort_order(),
+        }
+    }
+
+    /// Search query or the active prompt.
+    fn render_header(&self, cols: usize) {
+        let header = match &self.prompt {
+            Some(prompt) => Text::new(format!("{}: {}_", prompt.label, prompt.input))
+                .color_range(2, 0..prompt.label.len() + 1),
+            None => {
+                let label = format!("Search ({})", self.sort_order().label());
+
+                Text::new(format!("{}: {}_", label, self.search_query))
+                    .color_range(2, 0..label.len() + 1)
+            }
+        };
+
+        print_text_with_coordinates(header, 0, 0, Some(cols), None);
+    }
+
+    fn render_footer(&self, rows: usize, cols: usize, footer: &str) {
+        match &self.status {
+            Some(Status::Progress(text)) => {
+                print_text_with_coordinates(Text::new(text), 0, rows - 2, Some(cols), None);
+            }
+            Some(Status::Error(text)) => {
+                print_text_with_coordinates(
+                    Text::new(text).color_range(3, ..),
+                    0,
+                    rows - 2,
+                    Some(cols),
+                    None,
+                );
+            }
+            None => {}
+        }
+
+        let mut text = Text::new(footer);
+        let mut offset = 0;
+        while let Some(start) = footer[offset..].find('<') {
+            let start = offset + start;
+            let end = match footer[start..].find('>') {
+                Some(end) => start + end + 1,
+                None => break,
+            };
+
+            text = text.color_range(3, start..end);
+            offset = end;
+        }
+
+        print_text_with_coordinates(text, 0, rows - 1, Some(cols), None);
+    }
 }
 
 impl ZellijPlugin for State {
@@ -46,30 +528,31 @@ impl ZellijPlugin for State {
             PermissionType::ReadApplicationState,
             PermissionType::ChangeApplicationState,
             PermissionType::RunCommands,
+            PermissionType::ReadCliPipes,
         ]);
         subscribe(&[
             EventType::PermissionRequestResult,
             EventType::SessionUpdate,
             EventType::Key,
+            EventType::Mouse,
             EventType::RunCommandResult,
         ]);
 
         self.hidden = false;
         self.search_query = "".to_owned();
-        self.session_list = SessionList::new();
-        self.new_session_list = NewSessionList::new(config.get("base_path").map(|s| s.to_owned()));
+        self.session_list = SessionList::new(&config);
+        self.mode = Mode::from_config(&config);
+        self.source = DirectorySource::from_config(&config);
+        self.new_session_list = NewSessionList::new(&config);
         self.new_session_list.load_cache();
-        self.find_command = config.get("find_command").map(|i| i.to_owned());
     }
 
     fn update(&mut self, event: Event) -> bool {
-        let is_new_session = self.find_command.is_some();
-        if !self.queried_files {
-            if let Some(find_command) = self.find_command.clone() {
-                tracing::debug!("fetching files with: {find_command}");
-                query_list(&find_command);
-                self.queried_files = true;
-            }
+        let is_new_session = self.source.is_some();
+        if !self.queried_files && is_new_session && self.mode == Mode::List {
+            tracing::debug!("fetching files with: {:?}", self.source);
+            self.new_session_list.query();
+            self.queried_files = true;
         }
 
         let mut should_render = false;
@@ -78,32 +561,178 @@ impl ZellijPlugin for State {
             Event::PermissionRequestResult(_) => {
                 should_render = true;
             }
-            Event::RunCommandResult(_code, stdout, _stderr, _ctx) => {
-                let stdout = String::from_utf8(stdout.clone())
-                    .expect("")
-                    .split('\n')
-                    .map(|s| s.to_owned())
-                    .collect::<Vec<String>>();
+            Event::RunCommandResult(code, stdout, stderr, ctx) => {
+                let stdout = String::from_utf8_lossy(&stdout).to_string();
+                let stderr = String::from_utf8_lossy(&stderr).to_string();
+
+                tracing::debug!("got result {:?} {:?}", ctx, stdout);
+
+                match ctx.get("action").map(|a| a.as_str()) {
+                    Some("list") => {
+                        self.new_session_list.update_from_output(&stdout);
+                        self.new_session_list.save_cache();
+                        should_render = true;
+                    }
+                    Some("worktrees") => {
+                        if let Some(path) = ctx.get("path") {
+                            self.new_session_list.update_worktrees(path, code, &stdout);
+                            should_render = true;
+                        }
+                    }
+                    Some("clone") => {
+                        self.handle_cloned(code, &stderr, &ctx);
+                        should_render = true;
+                    }
+                    Some("manifest") => {
+                        self.handle_manifest(code, &stdout, &ctx);
+                        should_render = true;
+                    }
+                    Some("mkdir") => {
+                        self.handle_directory_created(code, &stderr, &ctx);
+                        should_render = true;
+                    }
+                    Some("scratch_init") => {
+                        self.handle_scratch_initialized(code, &stderr, &ctx);
+                        should_render = true;
+                    }
+                    Some("worktree_add") => {
+                        self.handle_worktree_added(code, &stderr, &ctx);
+                        should_render = true;
+                    }
+                    Some("preview") => {
+                        if let Some(path) = ctx.get("path") {
+                            self.new_session_list.update_preview(path, code, &stdout);
+                            should_render = true;
+                        }
+                    }
+                    Some("git_status") => {
+                        if let Some(path) = ctx.get("path") {
+                            self.new_session_list.update_git_status(path, code, &stdout);
+                            should_render = true;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::SessionUpdate(info, resurrectable_sessions) => {
+                self.resurrectable_sessions = resurrectable_sessions;
+                self.handle_sessions(&info);
 
-                tracing::debug!("got result {:?}", stdout);
-                tracing::debug!("got result");
+                let mut metadata = Metadata::load();
+                metadata.update(&info, &self.resurrectable_sessions);
 
-                self.new_session_list.update_list(stdout);
-                self.new_session_list.save_cache();
+                self.session_list.update_sessions(info.clone(), &metadata);
+                self.new_session_list.update_sessions(info, &metadata);
+
+                self.has_sessions = true;
+                for (command, pipe_id) in std::mem::take(&mut self.pending_commands) {
+                    self.run_pipe_command(command, pipe_id);
+                }
+                should_render = true;
+            }
+            Event::Key(key) if self.mode == Mode::Toggle && key.bare_key == BareKey::Esc => {
+                close_self();
+            }
+            Event::Key(_) if self.mode == Mode::Toggle => {}
+            Event::Key(key) if self.prompt.is_some() => {
+                self.handle_prompt_key(key);
+                should_render = true;
+            }
+            Event::Key(key) if self.help.is_some() => {
+                self.handle_help_key(key);
                 should_render = true;
             }
-            Event::SessionUpdate(info, _foo) => {
-                self.session_list.update_sessions(info.clone());
-                self.new_session_list.update_sessions(info);
+            Event::Key(key) if self.awaiting_hint => {
+                self.awaiting_hint = false;
+                self.status = None;
+
+                if let BareKey::Char(hint) = key.bare_key {
+                    self.jump(hint);
+                }
                 should_render = true;
             }
+            Event::Mouse(_)
+                if self.mode == Mode::Toggle || self.prompt.is_some() || self.help.is_some() => {}
+            Event::Mouse(mouse) => {
+                should_render = self.handle_mouse(mouse);
+            }
             Event::Key(key) => match key.bare_key {
-                BareKey::Enter => {
+                BareKey::Char('j') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
+                    self.awaiting_hint = true;
+                    self.status = Some(Status::Progress("Jump to hint…".to_owned()));
+                    should_render = true;
+                }
+                BareKey::Char(hint)
+                    if hint.is_ascii_digit() && key.has_modifiers(&[KeyModifier::Alt]) =>
+                {
+                    self.jump(hint);
+                    should_render = true;
+                }
+                BareKey::Char('?') => {
+                    self.help = Some(0);
+                    should_render = true;
+                }
+                BareKey::Char('w') if is_new_session && key.has_modifiers(&[KeyModifier::Ctrl]) => {
+                    self.prompt = Some(Prompt {
+                        label: "New worktree branch",
+                        input: "".to_owned(),
+                        action: PromptAction::Worktree,
+                    });
+                    should_render = true;
+                }
+                BareKey::Char('l')
+                    if !is_new_session && key.has_modifiers(&[KeyModifier::Ctrl]) =>
+                {
+                    if self.session_list.selected_session_name().is_some() {
+                        self.prompt = Some(Prompt {
+                            label: "Tags",
+                            input: self.session_list.selected_tags(),
+                            action: PromptAction::Tags,
+                        });
+                    }
+                    should_render = true;
+                }
+                BareKey::Char('r') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
                     if is_new_session {
-                        self.new_session_list.create_or_attach();
+                        self.new_session_list.cycle_sort();
                     } else {
-                        self.session_list.attach_selected();
+                        self.session_list.cycle_sort();
                     }
+                    should_render = true;
+                }
+                BareKey::Char('e')
+                    if !is_new_session && key.has_modifiers(&[KeyModifier::Ctrl]) =>
+                {
+                    self.session_list.expand_all();
+                    should_render = true;
+                }
+                BareKey::Char('c')
+                    if !is_new_session && key.has_modifiers(&[KeyModifier::Ctrl]) =>
+                {
+                    self.session_list.collapse_all();
+                    should_render = true;
+                }
+                BareKey::Char('f') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
+                    if is_new_session {
+                        self.new_session_list.toggle_pin_selected();
+                    } else {
+                        self.session_list.toggle_pin_selected();
+                    }
+                    should_render = true;
+                }
+                BareKey::Enter => {
+                    if is_new_session {
+                        let result = self.new_session_list.create_or_attach();
+                        self.show_opened(result);
+
+                        return true;
+                    }
+
+                    if self.session_list.toggle_selected_group() {
+                        return true;
+                    }
+
+                    self.session_list.attach_selected();
                     close_self();
                 }
                 BareKey::Delete => {
@@ -115,9 +744,7 @@ impl ZellijPlugin for State {
                 }
                 BareKey::Backspace => {
                     if self.search_query.is_empty() {
-                        if is_new_session {
-                            self.new_session_list.filter(&self.search_query);
-                        }
+                        self.filter();
 
                         return false;
                     }
@@ -128,9 +755,7 @@ impl ZellijPlugin for State {
                         .take(self.search_query.len() - 1)
                         .collect();
 
-                    if is_new_session {
-                        self.new_session_list.filter(&self.search_query);
-                    }
+                    self.filter();
 
                     should_render = true;
                 }
@@ -151,11 +776,19 @@ impl ZellijPlugin for State {
                     should_render = true;
                 }
                 BareKey::Left => {
-                    self.session_list.shrink();
+                    if is_new_session {
+                        self.new_session_list.shrink();
+                    } else {
+                        self.session_list.shrink();
+                    }
                     should_render = true;
                 }
                 BareKey::Right => {
-                    self.session_list.expand();
+                    if is_new_session {
+                        self.new_session_list.expand();
+                    } else {
+                        self.session_list.expand();
+                    }
                     should_render = true;
                 }
                 BareKey::Esc => {
@@ -163,16 +796,12 @@ impl ZellijPlugin for State {
                 }
                 BareKey::Char(' ') => {
                     self.search_query = self.search_query.clone() + " ";
-                    if is_new_session {
-                        self.new_session_list.filter(&self.search_query);
-                    }
+                    self.filter();
                     should_render = true
                 }
                 _ => {
                     self.search_query = self.search_query.clone() + &key.to_string();
-                    if is_new_session {
-                        self.new_session_list.filter(&self.search_query);
-                    }
+                    self.filter();
                     should_render = true
                 }
             },
@@ -182,56 +811,87 @@ impl ZellijPlugin for State {
         should_render
     }
 
+    fn pipe(&mut self, pipe_message: PipeMessage) -> bool {
+        let pipe_id = match &pipe_message.source {
+            PipeSource::Cli(pipe_id) => Some(pipe_id.clone()),
+            _ => None,
+        };
+
+        let command = match Command::parse(&pipe_message.name, pipe_message.payload.as_deref()) {
+            Ok(command) => command,
+            Err(e) => {
+                if let Some(pipe_id) = pipe_id {
+                    cli_pipe_output(&pipe_id, &format!("{e}\n"));
+                }
+
+                return false;
+            }
+        };
+
+        if !self.has_sessions {
+            if let Some(pipe_id) = &pipe_id {
+                block_cli_pipe_input(pipe_id);
+            }
+            self.pending_commands.push((command, pipe_id));
+
+            return false;
+        }
+
+        self.run_pipe_command(command, pipe_id);
+
+        true
+    }
+
     fn render(&mut self, rows: usize, cols: usize) {
-        if self.find_command.is_some() {
-            print_text_with_coordinates(
-                Text::new(format!("Search: {}_", self.search_query)).color_range(2, 0..7),
-                0,
-                0,
-                Some(cols),
-                None,
-            );
+        self.list_cols = 0;
 
+        if self.mode == Mode::Toggle {
+            self.render_footer(rows, cols, "Exit: <Esc>");
+            return;
+        }
+
+        if let Some(offset) = self.help {
+            let lines = help::lines(self.source.is_some(), &self.settings());
+
+            for (y, line) in lines.into_iter().skip(offset).take(rows - 2).enumerate() {
+                print_text_with_coordinates(line, 0, y, Some(cols), Some(1));
+            }
+
+            self.render_footer(rows, cols, "Scroll: <Up/Down> // Close: <?>");
+            return;
+        }
+
+        if self.source.is_some() {
+            self.render_header(cols);
+
+            let list_cols = list_columns(cols);
+            self.list_cols = list_cols;
             let list = self.new_session_list.get_list(rows - 5);
-            print_nested_list_with_coordinates(list, 0, 2, Some(cols), None);
-
-            print_text_with_coordinates(
-                Text::new("Attach: <Enter> // Delete: <Del> // Exit: <Esc>")
-                    .color_range(3, 8..15)
-                    .color_range(3, 27..32)
-                    .color_range(3, 42..47),
-                0,
-                rows - 1,
-                Some(cols),
-                None,
-            );
+            print_nested_list_with_coordinates(list, 0, LIST_Y, Some(list_cols), None);
+
+            if list_cols < cols {
+                let preview = self.new_session_list.preview();
+                render_preview(preview, rows, cols);
+            }
+
+            self.render_footer(rows, cols, &help::footer_line(help::NEW_SESSION_KEYS));
             return;
         }
 
         tracing::debug!("search query: {}", self.search_query);
 
-        print_text_with_coordinates(
-            Text::new(format!("Search: {}_", self.search_query)).color_range(2, 0..7),
-            0,
-            0,
-            Some(cols),
-            None,
-        );
-
-        self.session_list.filter(&self.search_query);
-
-        let list = self.session_list.get_list();
-        print_nested_list_with_coordinates(list, 0, 2, Some(cols), None);
-
-        print_text_with_coordinates(
-            Text::new("Attach: <Enter> // Delete: <Del> // Exit: <Esc>")
-                .color_range(3, 8..15)
-                .color_range(3, 27..32)
-                .color_range(3, 42..47),
-            0,
-            rows - 1,
-            Some(cols),
-            None,
-        );
+        self.render_header(cols);
+
+        let list_cols = list_columns(cols);
+        self.list_cols = list_cols;
+        let list = self.session_list.get_list(rows - 5);
+        print_nested_list_with_coordinates(list, 0, LIST_Y, Some(list_cols), None);
+
+        if list_cols < cols {
+            let (width, height) = preview_size(rows, cols);
+            render_preview(self.session_list.preview(width, height), rows, cols);
+        }
+
+        self.render_footer(rows, cols, &help::footer_line(help::SESSION_KEYS));
     }
 }
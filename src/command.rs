@@ -0,0 +1,16 @@
+use std::collections::BTreeMap;
+
+/// Context of a background command. Commands are started with the
+/// `run_command` shims and report back as `RunCommandResult` with this
+/// context: `action` selects the handler in the plugin's `update`, the other
+/// entries are its arguments, e.g. the `path` and `name` of a session.
+pub fn context(action: &str, entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+    let mut context = BTreeMap::new();
+    context.insert("action".to_owned(), action.to_owned());
+
+    for (key, value) in entries {
+        context.insert((*key).to_owned(), (*value).to_owned());
+    }
+
+    context
+}
@@ -0,0 +1,272 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use zellij_tile::{
+    prelude::SessionInfo,
+    shim::{NestedListItem, Text},
+};
+
+use crate::metadata::SessionMetadata;
+
+pub const DEFAULT_SESSION_FORMAT: &str =
+    "{name} ({tabs} tabs, {panes} panes) [{clients} connected users] {activity}";
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Text(String),
+    Field { name: String, color: Option<usize> },
+}
+
+/// Template for the rows of sessions, configured with `session_format`.
+/// Fields are written as `{name}` or, with a color slot from 0 to 3, as
+/// `{name:2}`, e.g. `{name} {tabs}t {panes}p {clients:3}c {age}`. Besides
+/// the counts, `{age}`, `{activity}` and `{cwd}` show the session metadata.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RowFormat {
+    segments: Vec<Segment>,
+    template: String,
+}
+
+impl Default for RowFormat {
+    fn default() -> Self {
+        Self::parse(DEFAULT_SESSION_FORMAT)
+    }
+}
+
+impl RowFormat {
+    pub fn from_config(config: &BTreeMap<String, String>) -> Self {
+        config
+            .get("session_format")
+            .map(|template| Self::parse(template))
+            .unwrap_or_default()
+    }
+
+    /// Parse a template. A `{` without a closing `}` is kept as text.
+    pub fn parse(template: &str) -> Self {
+        let mut segments = vec![];
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => break,
+            };
+
+            if start > 0 {
+                segments.push(Segment::Text(rest[..start].to_owned()));
+            }
+
+            let field = &rest[start + 1..end];
+            let (name, color) = match field.split_once(':') {
+                Some((name, color)) => (name, color.parse().ok()),
+                None => (field, default_color(field)),
+            };
+            segments.push(Segment::Field {
+                name: name.to_owned(),
+                color,
+            });
+
+            rest = &rest[end + 1..];
+        }
+
+        if !rest.is_empty() {
+            segments.push(Segment::Text(rest.to_owned()));
+        }
+
+        Self {
+            segments,
+            template: template.to_owned(),
+        }
+    }
+
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// Append the template with the values of the fields to the row.
+    /// Unknown fields are left empty. Returns the range of the `name`
+    /// field, e.g. to highlight the matched characters.
+    pub fn render(
+        &self,
+        row: &mut RowText,
+        values: &BTreeMap<&str, String>,
+    ) -> Option<Range<usize>> {
+        let mut name_range = None;
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => {
+                    row.push(text, None);
+                }
+                Segment::Field { name, color } => {
+                    let value = values.get(name.as_str()).map(|v| v.as_str());
+                    let range = row.push(value.unwrap_or_default(), *color);
+
+                    if name == "name" {
+                        name_range = Some(range);
+                    }
+                }
+            }
+        }
+
+        name_range
+    }
+}
+
+fn default_color(field: &str) -> Option<usize> {
+    match field {
+        "name" | "clients" => Some(0),
+        "tabs" => Some(1),
+        "panes" => Some(2),
+        _ => None,
+    }
+}
+
+/// Text of a row with its color ranges. Ranges are counted in characters,
+/// like zellij does, so that they stay correct for non-ASCII text.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RowText {
+    text: String,
+    len: usize,
+    colors: Vec<(usize, Range<usize>)>,
+}
+
+impl RowText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append text, optionally colored, and return its range.
+    pub fn push(&mut self, text: &str, color: Option<usize>) -> Range<usize> {
+        let start = self.len;
+        self.text.push_str(text);
+        self.len += text.chars().count();
+
+        if let Some(color) = color {
+            if start < self.len {
+                self.colors.push((color, start..self.len));
+            }
+        }
+
+        start..self.len
+    }
+
+    pub fn text(&self) -> Text {
+        self.colors
+            .iter()
+            .fold(Text::new(&self.text), |text, (color, range)| {
+                text.color_range(*color, range.clone())
+            })
+    }
+
+    pub fn item(&self) -> NestedListItem {
+        self.colors
+            .iter()
+            .fold(NestedListItem::new(&self.text), |item, (color, range)| {
+                item.color_range(*color, range.clone())
+            })
+    }
+}
+
+/// Number of selectable panes in all tabs of the session.
+pub fn pane_count(session: &SessionInfo) -> usize {
+    session
+        .panes
+        .panes
+        .values()
+        .flatten()
+        .filter(|pane| pane.is_selectable)
+        .count()
+}
+
+/// Values of the fields for a session.
+pub fn session_values(
+    name: &str,
+    session: &SessionInfo,
+    metadata: Option<&SessionMetadata>,
+) -> BTreeMap<&'static str, String> {
+    let mut values = BTreeMap::new();
+    values.insert("name", name.to_owned());
+    values.insert("tabs", session.tabs.len().to_string());
+    values.insert("panes", pane_count(session).to_string());
+    values.insert("clients", session.connected_clients.to_string());
+
+    if let Some(metadata) = metadata {
+        values.insert("age", metadata.age());
+        values.insert("activity", metadata.activity());
+        values.insert("cwd", metadata.cwd.clone().unwrap_or_default());
+    }
+
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use zellij_tile::prelude::PaneInfo;
+
+    fn text(text: &str) -> Segment {
+        Segment::Text(text.to_owned())
+    }
+
+    fn field(name: &str, color: Option<usize>) -> Segment {
+        Segment::Field {
+            name: name.to_owned(),
+            color,
+        }
+    }
+
+    #[test]
+    fn parse_fields() {
+        assert_eq!(
+            RowFormat::parse("{name} {tabs}t {clients:3} {age:x}").segments,
+            vec![
+                field("name", Some(0)),
+                text(" "),
+                field("tabs", Some(1)),
+                text("t "),
+                field("clients", Some(3)),
+                text(" "),
+                field("age", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_field_is_text() {
+        assert_eq!(
+            RowFormat::parse("{name} {tabs").segments,
+            vec![field("name", Some(0)), text(" {tabs")]
+        );
+        assert_eq!(RowFormat::parse("{").segments, vec![text("{")]);
+        assert_eq!(RowFormat::parse("").segments, vec![]);
+    }
+
+    #[test]
+    fn render_counts_characters() {
+        let values = vec![("name", "ünïcode".to_owned()), ("tabs", "2".to_owned())]
+            .into_iter()
+            .collect();
+
+        let mut row = RowText::new();
+        row.push("→ ", None);
+        let name = RowFormat::parse("{name} ({tabs}) {unknown}!").render(&mut row, &values);
+
+        assert_eq!(row.text, "→ ünïcode (2) !");
+        assert_eq!(name, Some(2..9));
+    }
+
+    #[test]
+    fn pane_count_counts_selectable_panes_of_all_tabs() {
+        let pane = |is_selectable| PaneInfo {
+            is_selectable,
+            ..Default::default()
+        };
+        let mut session = SessionInfo::default();
+        session.panes.panes.insert(0, vec![pane(true), pane(false)]);
+        session.panes.panes.insert(1, vec![pane(true), pane(true)]);
+
+        assert_eq!(pane_count(&session), 3);
+        assert_eq!(session_values("a", &session, None)["panes"], "3");
+    }
+}
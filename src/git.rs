@@ -0,0 +1,452 @@
+use std::collections::BTreeMap;
+
+use zellij_tile::shim::run_command;
+
+use crate::command;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GitStatus {
+    pub branch: String,
+    pub dirty: bool,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+impl GitStatus {
+    /// Short summary like `main* +1 -2`, that is appended to directory rows.
+    pub fn summary(&self) -> String {
+        let mut summary = self.branch.clone();
+
+        if self.dirty {
+            summary.push('*');
+        }
+
+        if self.ahead > 0 {
+            summary.push_str(&format!(" +{}", self.ahead));
+        }
+
+        if self.behind > 0 {
+            summary.push_str(&format!(" -{}", self.behind));
+        }
+
+        summary
+    }
+
+    /// Parse the output of `git status --porcelain=v2 --branch`.
+    pub fn parse(output: &str) -> Self {
+        let mut status = Self::default();
+
+        for line in output.lines() {
+            if let Some(head) = line.strip_prefix("# branch.head ") {
+                status.branch = head.trim().to_owned();
+                continue;
+            }
+
+            if let Some(ab) = line.strip_prefix("# branch.ab ") {
+                for part in ab.split_whitespace() {
+                    if let Some(ahead) = part.strip_prefix('+') {
+                        status.ahead = ahead.parse().unwrap_or(0);
+                    }
+                    if let Some(behind) = part.strip_prefix('-') {
+                        status.behind = behind.parse().unwrap_or(0);
+                    }
+                }
+                continue;
+            }
+
+            if !line.is_empty() && !line.starts_with('#') {
+                status.dirty = true;
+            }
+        }
+
+        status
+    }
+
+    fn to_cache_line(&self, path: &str) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}",
+            path, self.branch, self.dirty, self.ahead, self.behind
+        )
+    }
+
+    fn from_cache_line(line: &str) -> Option<(String, Self)> {
+        let parts = line.split('\t').collect::<Vec<&str>>();
+        if parts.len() != 5 {
+            return None;
+        }
+
+        Some((
+            parts[0].to_owned(),
+            Self {
+                branch: parts[1].to_owned(),
+                dirty: parts[2] == "true",
+                ahead: parts[3].parse().ok()?,
+                behind: parts[4].parse().ok()?,
+            },
+        ))
+    }
+}
+
+/// Query the git status of a directory in the background.
+pub fn query_status(path: &str) {
+    let context = command::context("git_status", &[("path", path)]);
+
+    run_command(
+        &["git", "-C", path, "status", "--porcelain=v2", "--branch"],
+        context,
+    );
+}
+
+pub fn load_cache() -> BTreeMap<String, GitStatus> {
+    std::fs::read_to_string("/cache/git_status")
+        .unwrap_or_default()
+        .lines()
+        .flat_map(GitStatus::from_cache_line)
+        .collect()
+}
+
+pub fn save_cache(statuses: &BTreeMap<String, GitStatus>) {
+    let content = statuses
+        .iter()
+        .map(|(path, status)| status.to_cache_line(path))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let _ = std::fs::write("/cache/git_status", content);
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Worktree {
+    pub path: String,
+    pub branch: Option<String>,
+}
+
+impl Worktree {
+    /// Name of the checked out branch or the directory name for detached
+    /// worktrees.
+    pub fn label(&self) -> String {
+        match &self.branch {
+            Some(branch) => branch.to_owned(),
+            None => crate::path::session_name(&self.path),
+        }
+    }
+
+    pub fn session_name(&self, repo_name: &str) -> String {
+        worktree_session_name(repo_name, &self.label())
+    }
+}
+
+/// Session name for a worktree, e.g. `repo_feature_login` for the branch
+/// `feature/login` of `repo`.
+pub fn worktree_session_name(repo_name: &str, branch: &str) -> String {
+    format!("{}_{}", repo_name, branch.replace(['/', '.'], "_"))
+}
+
+/// List the worktrees of a repository in the background.
+pub fn query_worktrees(path: &str) {
+    let context = command::context("worktrees", &[("path", path)]);
+
+    run_command(
+        &["git", "-C", path, "worktree", "list", "--porcelain"],
+        context,
+    );
+}
+
+/// Parse the output of `git worktree list --porcelain`. Bare repositories
+/// are skipped, since no session can be started in them.
+pub fn parse_worktrees(output: &str) -> Vec<Worktree> {
+    let mut worktrees = vec![];
+    let mut current: Option<Worktree> = None;
+    let mut is_bare = false;
+
+    for line in output.lines().chain(std::iter::once("")) {
+        if line.is_empty() {
+            if let Some(worktree) = current.take() {
+                if !is_bare {
+                    worktrees.push(worktree);
+                }
+            }
+            is_bare = false;
+            continue;
+        }
+
+        if let Some(path) = line.strip_prefix("worktree ") {
+            current = Some(Worktree {
+                path: path.to_owned(),
+                branch: None,
+            });
+            continue;
+        }
+
+        if let Some(branch) = line.strip_prefix("branch ") {
+            if let Some(worktree) = current.as_mut() {
+                let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+                worktree.branch = Some(branch.to_owned());
+            }
+            continue;
+        }
+
+        if line == "bare" {
+            is_bare = true;
+        }
+    }
+
+    worktrees
+}
+
+/// Runs the remaining arguments, when the branch in `$0` is a valid branch
+/// name for `git check-ref-format`.
+const CHECK_BRANCH_SCRIPT: &str = r#"git check-ref-format --branch "$0" > /dev/null && exec "$@""#;
+
+/// Reject branch names, that git would parse as options. All other names
+/// are checked with `git check-ref-format` by `add_worktree`.
+pub fn check_branch_name(branch: &str) -> Result<(), String> {
+    match branch {
+        "" => Err("Missing branch name".to_owned()),
+        branch if branch.starts_with('-') => Err(format!("Invalid branch name {branch}")),
+        _ => Ok(()),
+    }
+}
+
+/// Add a worktree for `branch` at `path` in the background. Without
+/// `create_branch`, an existing branch is checked out; otherwise a new
+/// branch is created with `-b`. Invalid branch names fail without running
+/// `git worktree add`.
+pub fn add_worktree(repo: &str, branch: &str, path: &str, name: &str, create_branch: bool) {
+    let context = command::context(
+        "worktree_add",
+        &[
+            ("repo", repo),
+            ("branch", branch),
+            ("path", path),
+            ("name", name),
+            ("create_branch", &create_branch.to_string()),
+        ],
+    );
+
+    let mut command = vec!["sh", "-c", CHECK_BRANCH_SCRIPT, branch];
+    match create_branch {
+        true => command.extend(["git", "-C", repo, "worktree", "add", "-b", branch, path]),
+        false => command.extend(["git", "-C", repo, "worktree", "add", path, branch]),
+    };
+
+    run_command(&command, context);
+}
+
+/// Detect a clonable repository in the search query. Full URLs (https,
+/// ssh, git, file), scp-like `git@host:owner/repo` addresses and local
+/// `.git` paths are taken as they are; `clone:owner/repo` is expanded to an
+/// https URL on `host`. A plain `owner/repo` is not taken, since it is the
+/// same as a search for a relative path.
+pub fn clone_url(query: &str, host: &str) -> Option<String> {
+    let query = query.trim();
+
+    if query.is_empty() || query.contains(' ') {
+        return None;
+    }
+
+    let schemes = ["https://", "http://", "ssh://", "git://", "file://"];
+    if schemes.iter().any(|scheme| query.starts_with(scheme)) {
+        return Some(query.to_owned());
+    }
+
+    if query.starts_with("git@") && query.contains(':') {
+        return Some(query.to_owned());
+    }
+
+    if query.starts_with('/') && query.trim_end_matches('/').ends_with(".git") {
+        return Some(query.to_owned());
+    }
+
+    let shorthand = query.strip_prefix("clone:")?;
+    let parts = shorthand.split('/').collect::<Vec<&str>>();
+    let is_valid_part = |part: &&str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+    };
+
+    if parts.len() == 2 && parts.iter().all(is_valid_part) {
+        return Some(format!("https://{host}/{shorthand}.git"));
+    }
+
+    None
+}
+
+/// Directory name, that `git clone` would pick for the URL.
+pub fn clone_directory_name(url: &str) -> String {
+    let url = url.trim_end_matches('/');
+    let name = url.rsplit(['/', ':']).next().unwrap_or(url);
+
+    name.strip_suffix(".git").unwrap_or(name).to_owned()
+}
+
+/// Clone the repository into `path` in the background.
+pub fn clone(url: &str, path: &str, name: &str) {
+    let context = command::context("clone", &[("path", path), ("name", name)]);
+
+    run_command(&["git", "clone", "--", url, path], context);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::process::Command;
+
+    const HOST: &str = "github.com";
+
+    #[test]
+    fn clone_urls() {
+        let urls = [
+            "https://github.com/zellij-org/zellij.git",
+            "http://example.com/repo",
+            "ssh://git@example.com/owner/repo.git",
+            "git://example.com/repo.git",
+            "file:///srv/git/repo.git",
+            "git@github.com:zellij-org/zellij.git",
+            "/srv/git/repo.git/",
+        ];
+
+        for url in urls {
+            assert_eq!(clone_url(url, HOST).as_deref(), Some(url));
+        }
+
+        assert_eq!(
+            clone_url(" clone:zellij-org/zellij ", "example.com").as_deref(),
+            Some("https://example.com/zellij-org/zellij.git")
+        );
+    }
+
+    #[test]
+    fn no_clone_urls() {
+        let queries = [
+            "",
+            "rust/zellij",
+            "zellij",
+            "clone:zellij",
+            "clone:a/b/c",
+            "clone:a/",
+            "clone:a b/c",
+            "https://example.com/a repo",
+            "git@github.com",
+            "/srv/git/repo",
+        ];
+
+        for query in queries {
+            assert_eq!(clone_url(query, HOST), None, "{}", query);
+        }
+    }
+
+    #[test]
+    fn clone_directory_names() {
+        assert_eq!(
+            clone_directory_name("https://github.com/a/zellij.git"),
+            "zellij"
+        );
+        assert_eq!(
+            clone_directory_name("https://github.com/a/zellij/"),
+            "zellij"
+        );
+        assert_eq!(clone_directory_name("git@github.com:zellij.git"), "zellij");
+        assert_eq!(clone_directory_name("/srv/git/repo.git"), "repo");
+    }
+
+    /// `clone_directory_name` has to match the directory, that `git clone`
+    /// creates, since the session is opened there.
+    #[test]
+    fn clone_directory_name_matches_git() {
+        let root = std::env::temp_dir().join(format!("zj-clone-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .args(args)
+                .current_dir(&root)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?}", args);
+        };
+
+        git(&["init", "--quiet", "--bare", "project.git"]);
+
+        let url = clone_url(&format!("{}/project.git", root.display()), HOST).unwrap();
+        git(&["clone", "--quiet", &url]);
+
+        assert!(root.join(clone_directory_name(&url)).join(".git").is_dir());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn status() {
+        let output = "# branch.oid 1234\n# branch.head main\n# branch.upstream origin/main\n# branch.ab +2 -1\n1 .M N... 100644 100644 100644 a b src/lib.rs\n";
+
+        let status = GitStatus::parse(output);
+
+        assert_eq!(
+            status,
+            GitStatus {
+                branch: "main".to_owned(),
+                dirty: true,
+                ahead: 2,
+                behind: 1,
+            }
+        );
+        assert_eq!(status.summary(), "main* +2 -1");
+    }
+
+    #[test]
+    fn status_of_detached_head() {
+        let status = GitStatus::parse("# branch.oid 1234\n# branch.head (detached)\n");
+
+        assert_eq!(status.branch, "(detached)");
+        assert!(!status.dirty);
+        assert_eq!(status.summary(), "(detached)");
+    }
+
+    #[test]
+    fn malformed_status() {
+        assert_eq!(GitStatus::parse(""), GitStatus::default());
+        assert_eq!(GitStatus::parse("# branch.ab +x -\n").ahead, 0);
+    }
+
+    #[test]
+    fn worktrees() {
+        let output = "worktree /srv/repo.git\nbare\n\nworktree /srv/repo\nHEAD 1234\nbranch refs/heads/main\n\nworktree /srv/repo-fix\nHEAD 5678\ndetached\n";
+
+        let worktrees = parse_worktrees(output);
+
+        assert_eq!(
+            worktrees,
+            vec![
+                Worktree {
+                    path: "/srv/repo".to_owned(),
+                    branch: Some("main".to_owned()),
+                },
+                Worktree {
+                    path: "/srv/repo-fix".to_owned(),
+                    branch: None,
+                },
+            ]
+        );
+        assert_eq!(worktrees[1].label(), "repo-fix");
+    }
+
+    #[test]
+    fn malformed_worktrees() {
+        assert_eq!(parse_worktrees(""), vec![]);
+        assert_eq!(parse_worktrees("branch refs/heads/main\nbare\n"), vec![]);
+    }
+
+    #[test]
+    fn branch_names() {
+        assert_eq!(check_branch_name("feature/login"), Ok(()));
+        assert!(check_branch_name("").is_err());
+        assert!(check_branch_name("-f").is_err());
+        assert!(check_branch_name("--detach").is_err());
+    }
+}
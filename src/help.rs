@@ -0,0 +1,172 @@
+use zellij_tile::prelude::Text;
+
+/// Key of the plugin and what it does. Bindings with a `label` are also shown
+/// in the footer.
+pub struct Binding {
+    pub key: &'static str,
+    pub action: &'static str,
+    pub label: Option<&'static str>,
+}
+
+const fn bind(key: &'static str, action: &'static str) -> Binding {
+    Binding {
+        key,
+        action,
+        label: None,
+    }
+}
+
+const fn footer(key: &'static str, action: &'static str, label: &'static str) -> Binding {
+    Binding {
+        key,
+        action,
+        label: Some(label),
+    }
+}
+
+pub const SESSION_KEYS: &[Binding] = &[
+    footer(
+        "Enter",
+        "Attach to the selected session, tab or pane, or fold the selected group",
+        "Attach",
+    ),
+    bind("Up / Down", "Move through the visible rows"),
+    bind("Right", "Expand the selected row or select its first child"),
+    bind("Left", "Collapse the selected row or select its parent"),
+    bind("Ctrl e", "Expand all sessions and tabs"),
+    bind("Ctrl c", "Collapse all sessions and tabs"),
+    footer(
+        "Ctrl j",
+        "Jump to the session with the hint typed next",
+        "Jump",
+    ),
+    bind("Alt 1-9", "Jump to the session with the hint"),
+    bind("Ctrl l", "Edit the tags of the selected session"),
+    bind("Ctrl r", "Cycle the sort order"),
+    bind("Ctrl f", "Pin or unpin the selected session"),
+    bind("Del", "Kill the selected session"),
+    bind("Backspace", "Delete the last character of the search"),
+    bind("Wheel", "Move through the visible rows"),
+    bind(
+        "Click",
+        "Select a row or, on its arrow, expand or collapse it",
+    ),
+    bind("Double click", "Attach to the clicked row"),
+    footer("?", "Show or hide this help", "Help"),
+    footer("Esc", "Close the plugin", "Exit"),
+];
+
+pub const NEW_SESSION_KEYS: &[Binding] = &[
+    footer(
+        "Enter",
+        "Open a session in the selected directory or run the selected action",
+        "Attach",
+    ),
+    bind("Up / Down", "Move the selection"),
+    bind(
+        "Right",
+        "Expand the selected git repository into its worktrees",
+    ),
+    bind("Left", "Fold the worktrees"),
+    footer(
+        "Ctrl j",
+        "Jump to the directory with the hint typed next",
+        "Jump",
+    ),
+    bind("Alt 1-9", "Jump to the directory with the hint"),
+    bind("Ctrl w", "Add a git worktree to the selected repository"),
+    bind("Ctrl r", "Cycle the sort order"),
+    bind("Ctrl f", "Pin or unpin the selected directory"),
+    bind("Del", "Kill the session of the selected directory"),
+    bind("Backspace", "Delete the last character of the search"),
+    bind("Wheel", "Move the selection"),
+    bind("Click", "Select a directory, worktree or action"),
+    bind("Double click", "Open the clicked row"),
+    footer("?", "Show or hide this help", "Help"),
+    footer("Esc", "Close the plugin", "Exit"),
+];
+
+const SESSION_SEARCH: &[(&str, &str)] = &[
+    ("session", "Fuzzy search the sessions"),
+    (
+        "session tab",
+        "A space starts searching the tabs of the sessions",
+    ),
+    (
+        "session tab pane",
+        "A second space searches the panes of the tabs",
+    ),
+    (
+        "#tag",
+        "Only show sessions with the tag, anywhere in the search",
+    ),
+];
+
+const NEW_SESSION_SEARCH: &[(&str, &str)] = &[
+    ("directory", "Fuzzy search the directories"),
+    ("git url", "Offer to clone the repository into `clone_path`"),
+    (
+        "clone:owner/repo",
+        "Offer to clone the repository from `clone_host` into `clone_path`",
+    ),
+    (
+        "name",
+        "Offer to create a directory in `scratch_path`, when nothing matches",
+    ),
+];
+
+/// Footer with the labeled bindings, e.g. `Attach: <Enter> // Exit: <Esc>`.
+pub fn footer_line(bindings: &[Binding]) -> String {
+    bindings
+        .iter()
+        .filter_map(|binding| {
+            binding
+                .label
+                .map(|label| format!("{}: <{}>", label, binding.key))
+        })
+        .collect::<Vec<String>>()
+        .join(" // ")
+}
+
+fn section(lines: &mut Vec<Text>, title: &str, entries: &[(&str, &str)]) {
+    let width = entries
+        .iter()
+        .map(|(key, _)| key.chars().count())
+        .max()
+        .unwrap_or_default();
+
+    lines.push(Text::new(title).color_range(2, ..));
+
+    for (key, description) in entries {
+        let line = format!("  {:width$}  {}", key, description, width = width);
+        lines.push(Text::new(line).color_range(3, 2..2 + key.chars().count()));
+    }
+
+    lines.push(Text::new(""));
+}
+
+/// Lines of the help view with the bindings and the search syntax of the
+/// active list and the configuration values in effect.
+pub fn lines(is_new_session: bool, settings: &[(&str, String)]) -> Vec<Text> {
+    let (bindings, search) = match is_new_session {
+        true => (NEW_SESSION_KEYS, NEW_SESSION_SEARCH),
+        false => (SESSION_KEYS, SESSION_SEARCH),
+    };
+
+    let keys = bindings
+        .iter()
+        .map(|binding| (binding.key, binding.action))
+        .collect::<Vec<(&str, &str)>>();
+
+    let mut lines = vec![];
+    section(&mut lines, "Keys", &keys);
+    section(&mut lines, "Search", search);
+
+    let settings = settings
+        .iter()
+        .map(|(key, value)| (*key, value.as_str()))
+        .collect::<Vec<(&str, &str)>>();
+    section(&mut lines, "Configuration", &settings);
+
+    lines
+}
@@ -0,0 +1,22 @@
+/// Hints for jumping to a row. The first nine can also be reached with
+/// `Alt 1` to `Alt 9`; all of them after the leader key.
+const HINTS: &str = "123456789abcdefghijklmnopqrstuvwxyz";
+
+/// Hint of the row at `index` among the rows with hints in the visible
+/// window of the list, so that every visible row can be reached.
+pub fn hint(index: usize) -> Option<char> {
+    HINTS.chars().nth(index)
+}
+
+pub fn index(hint: char) -> Option<usize> {
+    HINTS.find(hint.to_ascii_lowercase())
+}
+
+/// Text in front of a row. Rows without a hint are padded, so that all
+/// rows stay aligned.
+pub fn prefix(index: usize) -> String {
+    match hint(index) {
+        Some(hint) => format!("{hint} "),
+        None => "  ".to_owned(),
+    }
+}
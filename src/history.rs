@@ -0,0 +1,62 @@
+use zellij_tile::prelude::SessionInfo;
+
+const CACHE_PATH: &str = "/cache/history";
+
+/// Most recently used sessions, persisted in the plugin cache with the most
+/// recent session on the first line.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct History {
+    sessions: Vec<String>,
+}
+
+impl History {
+    pub fn load() -> Self {
+        let sessions = std::fs::read_to_string(CACHE_PATH)
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_owned())
+            .collect();
+
+        Self { sessions }
+    }
+
+    fn save(&self) {
+        let _ = std::fs::write(CACHE_PATH, self.sessions.join("\n"));
+    }
+
+    /// Move the session to the top and persist the change.
+    pub fn visit(&mut self, session: &str) {
+        if self.sessions.first().map(|s| s.as_str()) == Some(session) {
+            return;
+        }
+
+        self.sessions.retain(|s| s != session);
+        self.sessions.insert(0, session.to_owned());
+        self.save();
+    }
+
+    /// Score of a session for sorting, higher for more recently used ones.
+    pub fn recency(&self, session: &str) -> f64 {
+        self.sessions
+            .iter()
+            .position(|s| s == session)
+            .map(|position| (self.sessions.len() - position) as f64)
+            .unwrap_or_default()
+    }
+
+    /// Most recently used session, that is still running and not the
+    /// current one.
+    pub fn previous<'a>(&'a self, sessions: &[SessionInfo]) -> Option<&'a String> {
+        self.sessions.iter().find(|name| {
+            sessions
+                .iter()
+                .any(|s| &s.name == *name && !s.is_current_session)
+        })
+    }
+}
+
+/// Record an attach to the session.
+pub fn visit(session: &str) {
+    History::load().visit(session);
+}
@@ -0,0 +1,69 @@
+use crate::manifest::shell_quote;
+
+/// Runs the project hook file and the configured command one after another.
+/// Both are optional. Failures are reported in the pane, that then goes on
+/// with the shell of the session.
+const POST_CREATE_SCRIPT: &str = r#"
+if [ "$project_hooks" = "true" ] && [ -f .zj-session ]; then
+    sh ./.zj-session || echo "zj-smart-sessions: .zj-session failed with exit code $?"
+fi
+if [ -n "$post_create" ]; then
+    sh -c "$post_create" || echo "zj-smart-sessions: post_create failed with exit code $?"
+fi
+"#;
+
+/// Script for the first pane of a new session, that runs the post-create
+/// hooks in its cwd before the shell starts. The hooks get the session name
+/// in `ZJ_SESSION_NAME`. Without any hooks, there is no script.
+pub fn post_create(name: &str, command: Option<&str>, project_hooks: bool) -> Option<String> {
+    if command.is_none() && !project_hooks {
+        return None;
+    }
+
+    Some(format!(
+        "export ZJ_SESSION_NAME={}; project_hooks={}; post_create={};{}",
+        shell_quote(name),
+        project_hooks,
+        shell_quote(command.unwrap_or_default()),
+        POST_CREATE_SCRIPT
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::process::Command;
+
+    fn run(script: &str) -> String {
+        let output = Command::new("sh")
+            .args(["-c", &format!("{script}echo shell")])
+            .current_dir(std::env::temp_dir())
+            .output()
+            .unwrap();
+
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    #[test]
+    fn no_hooks() {
+        assert_eq!(post_create("api", None, false), None);
+    }
+
+    #[test]
+    fn hooks_get_the_session_name() {
+        let script = post_create("it's", Some("echo \"$ZJ_SESSION_NAME\""), false).unwrap();
+
+        assert_eq!(run(&script), "it's\nshell\n");
+    }
+
+    #[test]
+    fn failures_are_reported_and_the_shell_starts() {
+        let script = post_create("api", Some("exit 3"), false).unwrap();
+
+        assert_eq!(
+            run(&script),
+            "zj-smart-sessions: post_create failed with exit code 3\nshell\n"
+        );
+    }
+}
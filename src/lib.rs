@@ -1,2 +1,20 @@
+pub mod command;
+pub mod format;
+pub mod git;
+pub mod help;
+pub mod hints;
+pub mod history;
+pub mod hooks;
+pub mod listing;
+pub mod manifest;
+pub mod metadata;
 pub mod new_session_list;
+pub mod path;
+pub mod pins;
+pub mod pipe;
+pub mod preview;
+pub mod scratch;
 pub mod session_list;
+pub mod sort;
+pub mod tags;
+pub mod zoxide;
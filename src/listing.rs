@@ -0,0 +1,92 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use serde_json::{json, Value};
+use zellij_tile::prelude::{PaneInfo, SessionInfo, TabInfo};
+
+use crate::{metadata::Metadata, pins::Pins, tags::Tags};
+
+/// Sessions with their tabs and panes, resurrectable sessions and the
+/// frecency scores of directories as JSON, for `zellij pipe -- list`.
+pub fn to_json(
+    sessions: &[SessionInfo],
+    resurrectable_sessions: &[(String, Duration)],
+    frecency: &BTreeMap<String, f64>,
+) -> String {
+    let tags = Tags::load();
+    let pins = Pins::sessions();
+    let metadata = Metadata::load();
+
+    let sessions = sessions
+        .iter()
+        .map(|session| session_json(session, &tags, &pins, &metadata))
+        .collect::<Vec<Value>>();
+
+    let resurrectable_sessions = resurrectable_sessions
+        .iter()
+        .map(|(name, age)| {
+            json!({
+                "name": name,
+                "age_seconds": age.as_secs(),
+                "tags": tags.get(name).cloned().unwrap_or_default(),
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    json!({
+        "sessions": sessions,
+        "resurrectable_sessions": resurrectable_sessions,
+        "frecency": frecency,
+    })
+    .to_string()
+}
+
+fn session_json(session: &SessionInfo, tags: &Tags, pins: &Pins, metadata: &Metadata) -> Value {
+    let tabs = session
+        .tabs
+        .iter()
+        .map(|tab| {
+            let panes = session
+                .panes
+                .panes
+                .get(&tab.position)
+                .map(|panes| panes.as_slice())
+                .unwrap_or_default();
+
+            tab_json(tab, panes)
+        })
+        .collect::<Vec<Value>>();
+
+    json!({
+        "name": session.name,
+        "is_current_session": session.is_current_session,
+        "connected_clients": session.connected_clients,
+        "tags": tags.get(&session.name).cloned().unwrap_or_default(),
+        "pinned": pins.contains(&session.name),
+        "first_seen": metadata.get(&session.name).map(|m| m.first_seen),
+        "last_changed": metadata.get(&session.name).map(|m| m.last_changed),
+        "cwd": metadata.get(&session.name).and_then(|m| m.cwd.clone()),
+        "tabs": tabs,
+    })
+}
+
+fn tab_json(tab: &TabInfo, panes: &[PaneInfo]) -> Value {
+    let panes = panes
+        .iter()
+        .map(|pane| {
+            json!({
+                "id": pane.id,
+                "title": pane.title,
+                "is_plugin": pane.is_plugin,
+                "is_focused": pane.is_focused,
+                "is_floating": pane.is_floating,
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    json!({
+        "position": tab.position,
+        "name": tab.name,
+        "active": tab.active,
+        "panes": panes,
+    })
+}
@@ -0,0 +1,215 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use kdl::{KdlDocument, KdlNode};
+use zellij_tile::{prelude::LayoutInfo, shim::run_command_with_env_variables_and_cwd};
+
+use crate::command;
+
+pub const FILE_NAME: &str = ".zj-session.kdl";
+
+/// First line of the manifest query, when the directory has a `.zj-session`
+/// hook file. It is a KDL comment, so the manifest still parses.
+const HOOK_FILE_MARKER: &str = "// .zj-session";
+
+const LAYOUT_TEMPLATE: &str = r#"layout {
+    default_tab_template {
+        pane size=1 borderless=true {
+            plugin location="zellij:tab-bar"
+        }
+        children
+        pane size=2 borderless=true {
+            plugin location="zellij:status-bar"
+        }
+    }
+    tab {
+{panes}    }
+}
+"#;
+
+const PANE_TEMPLATE: &str = r#"        pane command="sh" {
+            args "-c" {script}
+        }
+"#;
+
+/// Per-project session configuration from a `.zj-session.kdl` file:
+///
+/// ```kdl
+/// name "api"
+/// layout "development"
+/// tags "work" "backend"
+/// env {
+///     RUST_LOG "debug"
+/// }
+/// commands {
+///     command "cargo watch -x check"
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Manifest {
+    pub name: Option<String>,
+    pub layout: Option<String>,
+    pub env: BTreeMap<String, String>,
+    pub commands: Vec<String>,
+    pub tags: Vec<String>,
+    /// Whether the directory has a `.zj-session` hook file.
+    pub has_hook_file: bool,
+}
+
+impl Manifest {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let document = content
+            .parse::<KdlDocument>()
+            .map_err(|e| format!("invalid {FILE_NAME}: {e}"))?;
+
+        let mut manifest = Self::default();
+
+        for node in document.nodes() {
+            match node.name().value() {
+                "name" => manifest.name = first_string(node),
+                "layout" => manifest.layout = first_string(node),
+                "tags" => manifest.tags = strings(node),
+                "env" => {
+                    for variable in children(node) {
+                        if let Some(value) = first_string(variable) {
+                            manifest
+                                .env
+                                .insert(variable.name().value().to_owned(), value);
+                        }
+                    }
+                }
+                "commands" => {
+                    manifest.commands = children(node)
+                        .iter()
+                        .filter(|command| command.name().value() == "command")
+                        .flat_map(first_string)
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    /// Layout for a new session. A configured layout is looked up in the
+    /// layouts known to zellij or, if it looks like a path, relative to the
+    /// project directory. Without a layout, one is generated for the
+    /// startup commands, environment variables and post-create `hooks`.
+    /// Configured layouts are used as they are, so the hooks are not run.
+    pub fn layout_info(
+        &self,
+        cwd: &str,
+        available_layouts: &[LayoutInfo],
+        hooks: Option<&str>,
+    ) -> Option<LayoutInfo> {
+        if let Some(layout) = &self.layout {
+            let known = available_layouts.iter().find(|info| match info {
+                LayoutInfo::BuiltIn(name) | LayoutInfo::File(name) => name == layout,
+                _ => false,
+            });
+
+            if let Some(known) = known {
+                return Some(known.clone());
+            }
+
+            let path = match layout.starts_with('/') {
+                true => layout.to_owned(),
+                false => format!("{}/{}", cwd.trim_end_matches('/'), layout),
+            };
+
+            return Some(LayoutInfo::File(path));
+        }
+
+        if self.commands.is_empty() && self.env.is_empty() && hooks.is_none() {
+            return None;
+        }
+
+        Some(LayoutInfo::Stringified(self.stringified_layout(hooks)))
+    }
+
+    /// Layout with a pane per startup command and a shell, all with the
+    /// configured environment variables. The hooks run in the shell pane
+    /// before the shell starts.
+    fn stringified_layout(&self, hooks: Option<&str>) -> String {
+        let exports = self
+            .env
+            .iter()
+            .fold(String::new(), |mut exports, (key, value)| {
+                let _ = write!(exports, "export {}={}; ", key, shell_quote(value));
+                exports
+            });
+
+        let mut panes = self
+            .commands
+            .iter()
+            .map(|command| format!("{exports}{command}"))
+            .collect::<Vec<String>>();
+        panes.push(format!(
+            "{exports}{}exec \"${{SHELL:-sh}}\"",
+            hooks.unwrap_or_default()
+        ));
+
+        let panes = panes
+            .iter()
+            .map(|script| PANE_TEMPLATE.replace("{script}", &kdl_quote(script)))
+            .collect::<String>();
+
+        LAYOUT_TEMPLATE.replace("{panes}", &panes)
+    }
+}
+
+fn children(node: &KdlNode) -> &[KdlNode] {
+    node.children().map(|c| c.nodes()).unwrap_or_default()
+}
+
+fn strings(node: &KdlNode) -> Vec<String> {
+    node.entries()
+        .iter()
+        .filter(|entry| entry.name().is_none())
+        .flat_map(|entry| entry.value().as_string())
+        .map(|value| value.to_owned())
+        .collect()
+}
+
+fn first_string(node: &KdlNode) -> Option<String> {
+    strings(node).into_iter().next()
+}
+
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn kdl_quote(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+
+    format!("\"{escaped}\"")
+}
+
+/// Whether the output of `query` reports a `.zj-session` hook file.
+pub fn has_hook_file(output: &str) -> bool {
+    output.starts_with(HOOK_FILE_MARKER)
+}
+
+/// Read the manifest of a directory in the background and check for a hook
+/// file. With `open`, the session for the directory is opened once it was
+/// read.
+pub fn query(path: &str, name: &str, open: bool) {
+    let context = command::context(
+        "manifest",
+        &[("path", path), ("name", name), ("open", &open.to_string())],
+    );
+    let script = format!(
+        "[ -f .zj-session ] && echo '{}'; cat {}",
+        HOOK_FILE_MARKER, FILE_NAME
+    );
+
+    run_command_with_env_variables_and_cwd(
+        &["sh", "-c", &script],
+        BTreeMap::new(),
+        path.into(),
+        context,
+    );
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    hash::{Hash, Hasher},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use zellij_tile::prelude::SessionInfo;
+
+const CACHE_PATH: &str = "/cache/metadata";
+
+/// What the plugin knows about a session beyond the `SessionUpdate`.
+/// Timestamps are seconds since the unix epoch.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SessionMetadata {
+    pub first_seen: u64,
+    pub last_changed: u64,
+    /// Directory, that the session was created in by the plugin.
+    pub cwd: Option<String>,
+    /// Hash of the tabs, panes and clients to detect changes.
+    fingerprint: u64,
+}
+
+impl SessionMetadata {
+    fn parse(line: &str) -> Option<(String, Self)> {
+        let mut fields = line.split('\t');
+        let name = fields.next()?.to_owned();
+        let first_seen = fields.next()?.parse().ok()?;
+        let last_changed = fields.next()?.parse().ok()?;
+        let fingerprint = fields.next()?.parse().ok()?;
+        let cwd = fields
+            .next()
+            .filter(|cwd| !cwd.is_empty())
+            .map(|cwd| cwd.to_owned());
+
+        Some((
+            name,
+            Self {
+                first_seen,
+                last_changed,
+                cwd,
+                fingerprint,
+            },
+        ))
+    }
+
+    fn line(&self, name: &str) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}",
+            name,
+            self.first_seen,
+            self.last_changed,
+            self.fingerprint,
+            self.cwd.as_deref().unwrap_or_default()
+        )
+    }
+
+    /// Time since the session was first seen, e.g. `3d`.
+    pub fn age(&self) -> String {
+        format_duration(now().saturating_sub(self.first_seen))
+    }
+
+    /// Time since the last change of tabs, panes or clients, e.g. `5m ago`.
+    pub fn activity(&self) -> String {
+        format!(
+            "{} ago",
+            format_duration(now().saturating_sub(self.last_changed))
+        )
+    }
+}
+
+/// Metadata of sessions, persisted in the plugin cache as lines of
+/// `session<TAB>first seen<TAB>last changed<TAB>fingerprint<TAB>cwd`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    by_session: BTreeMap<String, SessionMetadata>,
+}
+
+impl Metadata {
+    pub fn load() -> Self {
+        let by_session = std::fs::read_to_string(CACHE_PATH)
+            .unwrap_or_default()
+            .lines()
+            .flat_map(SessionMetadata::parse)
+            .collect();
+
+        Self { by_session }
+    }
+
+    fn save(&self) {
+        let content = self
+            .by_session
+            .iter()
+            .map(|(name, metadata)| metadata.line(name))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let _ = std::fs::write(CACHE_PATH, content);
+    }
+
+    pub fn get(&self, session: &str) -> Option<&SessionMetadata> {
+        self.by_session.get(session)
+    }
+
+    /// Add new sessions and update the last change of sessions, whose tabs,
+    /// panes or clients differ from the previous update. Sessions, that are
+    /// neither running nor resurrectable, are removed.
+    pub fn update(
+        &mut self,
+        sessions: &[SessionInfo],
+        resurrectable_sessions: &[(String, Duration)],
+    ) {
+        let now = now();
+        let mut changed = false;
+
+        self.by_session.retain(|name, _| {
+            let known = sessions.iter().any(|s| &s.name == name)
+                || resurrectable_sessions.iter().any(|(n, _)| n == name);
+            changed |= !known;
+
+            known
+        });
+
+        for session in sessions {
+            let fingerprint = fingerprint(session);
+
+            match self.by_session.get_mut(&session.name) {
+                Some(metadata) if metadata.fingerprint == fingerprint => {}
+                Some(metadata) => {
+                    metadata.fingerprint = fingerprint;
+                    metadata.last_changed = now;
+                    changed = true;
+                }
+                None => {
+                    let metadata = SessionMetadata {
+                        first_seen: now,
+                        last_changed: now,
+                        cwd: None,
+                        fingerprint,
+                    };
+                    self.by_session.insert(session.name.clone(), metadata);
+                    changed = true;
+                }
+            }
+        }
+
+        if changed {
+            self.save();
+        }
+    }
+}
+
+/// Remember the directory of a session, that is opened by the plugin.
+pub fn set_cwd(session: &str, cwd: &str) {
+    let mut metadata = Metadata::load();
+    let now = now();
+
+    let entry = metadata
+        .by_session
+        .entry(session.to_owned())
+        .or_insert_with(|| SessionMetadata {
+            first_seen: now,
+            last_changed: now,
+            ..Default::default()
+        });
+    entry.cwd = Some(cwd.to_owned());
+
+    metadata.save();
+}
+
+fn fingerprint(session: &SessionInfo) -> u64 {
+    let mut panes = session
+        .panes
+        .panes
+        .iter()
+        .flat_map(|(tab, panes)| panes.iter().map(move |pane| (*tab, pane.id, &pane.title)))
+        .collect::<Vec<(usize, u32, &String)>>();
+    panes.sort();
+
+    let tabs = session
+        .tabs
+        .iter()
+        .map(|tab| (tab.position, &tab.name))
+        .collect::<Vec<(usize, &String)>>();
+
+    let mut hasher = DefaultHasher::new();
+    (tabs, panes, session.connected_clients).hash(&mut hasher);
+
+    hasher.finish()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Short duration, e.g. `45s`, `5m`, `3h` or `2d`.
+pub fn format_duration(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{seconds}s"),
+        60..=3599 => format!("{}m", seconds / 60),
+        3600..=86399 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
@@ -1,14 +1,61 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Instant,
+};
 
 use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
 use zellij_tile::{
     prelude::SessionInfo,
-    shim::{kill_sessions, run_command, switch_session_with_cwd, NestedListItem},
+    shim::{
+        kill_sessions, run_command, switch_session_with_cwd, switch_session_with_focus,
+        switch_session_with_layout, NestedListItem, Text,
+    },
+};
+
+use crate::{
+    command,
+    format::{self, RowFormat, RowText},
+    git::{self, GitStatus, Worktree},
+    hints, history, hooks,
+    manifest::{self, Manifest},
+    metadata::{self, Metadata},
+    path,
+    pins::Pins,
+    preview, scratch,
+    session_list::DOUBLE_CLICK,
+    sort::{SortKey, SortOrder},
+    tags::Tags,
+    zoxide,
 };
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum DirectorySource {
+    FindCommand(String),
+    Zoxide,
+}
+
+impl DirectorySource {
+    pub fn from_config(config: &BTreeMap<String, String>) -> Option<Self> {
+        if config.get("source").map(|s| s.as_str()) == Some("zoxide") {
+            return Some(Self::Zoxide);
+        }
+
+        config
+            .get("find_command")
+            .map(|cmd| Self::FindCommand(cmd.to_owned()))
+    }
+
+    fn cache_path(&self) -> &'static str {
+        match self {
+            Self::FindCommand(_) => "/cache/store",
+            Self::Zoxide => "/cache/zoxide",
+        }
+    }
+}
+
 pub fn query_list(cmd: &str) {
     let command = commandline_parser(cmd);
-    let context = BTreeMap::new();
+    let context = command::context("list", &[]);
 
     run_command(
         &command.iter().map(|x| x.as_str()).collect::<Vec<&str>>(),
@@ -68,45 +115,182 @@ fn commandline_parser(input: &str) -> Vec<String> {
     output
 }
 
+/// Pseudo rows, that are shown after the matching directories.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ListAction {
+    Clone { url: String, path: String },
+    CreateDirectory { path: String },
+}
+
+impl ListAction {
+    fn label(&self) -> String {
+        match self {
+            Self::Clone { url, .. } => format!("Clone {url}"),
+            Self::CreateDirectory { path } => {
+                format!("Create new project {}", path::session_name(path))
+            }
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct NewSessionList {
     list: Vec<String>,
     session_list: Vec<SessionInfo>,
     filtered_list: Vec<(String, Vec<usize>)>,
     filtered_list_len: usize,
+    actions: Vec<ListAction>,
     selected_item_index: usize,
     search_query: String,
     matcher: SkimMatcherV2,
     max_items: Option<usize>,
     base_path: Option<String>,
+    home_dir: Option<String>,
+    source: Option<DirectorySource>,
+    frecency: BTreeMap<String, f64>,
+    show_git_status: bool,
+    git_status: BTreeMap<String, GitStatus>,
+    git_status_requested: BTreeSet<String>,
+    worktrees: BTreeMap<String, Vec<Worktree>>,
+    worktree_path: Option<String>,
+    clone_path: Option<String>,
+    clone_host: String,
+    scratch_path: Option<String>,
+    scratch_init: Option<String>,
+    post_create: Option<String>,
+    project_hooks: bool,
+    manifests: BTreeMap<String, Option<Manifest>>,
+    manifests_requested: BTreeSet<String>,
+    previews: BTreeMap<String, String>,
+    previews_requested: BTreeSet<String>,
+    format: RowFormat,
+    metadata: Metadata,
+    sort: SortOrder,
+    pins: Pins,
+    is_expanded: bool,
+    selected_worktree_index: usize,
+    /// First row of the last rendered window and the last clicked row, as
+    /// index of the row and of the worktree.
+    offset: usize,
+    last_click: Option<((usize, Option<usize>), Instant)>,
 }
 
 impl NewSessionList {
-    pub fn new(base_path: Option<String>) -> Self {
-        tracing::debug!("base_path {:?}", base_path);
+    pub fn new(config: &BTreeMap<String, String>) -> Self {
+        let home_dir = config
+            .get("home_dir")
+            .cloned()
+            .or_else(|| std::env::var("HOME").ok());
+        let base_path = config
+            .get("base_path")
+            .map(|base_path| path::expand_home(base_path, home_dir.as_deref()));
+
+        let show_git_status = config.get("git_status").map(|s| s.as_str()) == Some("true");
+
+        tracing::debug!("base_path {:?} home_dir {:?}", base_path, home_dir);
+
+        let worktree_path = config
+            .get("worktree_path")
+            .map(|worktree_path| path::expand_home(worktree_path, home_dir.as_deref()));
+
+        let clone_path = config
+            .get("clone_path")
+            .map(|clone_path| path::expand_home(clone_path, home_dir.as_deref()))
+            .or_else(|| base_path.clone())
+            .or_else(|| home_dir.clone());
+        let scratch_path = config
+            .get("scratch_path")
+            .map(|scratch_path| path::expand_home(scratch_path, home_dir.as_deref()))
+            .or_else(|| base_path.clone())
+            .or_else(|| home_dir.clone());
+        let clone_host = config
+            .get("clone_host")
+            .cloned()
+            .unwrap_or_else(|| "github.com".to_owned());
+
         Self {
             base_path,
+            home_dir,
+            worktree_path,
+            clone_path,
+            clone_host,
+            scratch_path,
+            scratch_init: config.get("scratch_init").cloned(),
+            post_create: config.get("post_create").cloned(),
+            project_hooks: config.get("project_hooks").map(|s| s.as_str()) == Some("true"),
+            pins: Pins::directories(),
+            format: RowFormat::from_config(config),
+            sort: SortOrder::from_config(config),
+            source: DirectorySource::from_config(config),
+            show_git_status,
+            git_status: match show_git_status {
+                true => git::load_cache(),
+                false => BTreeMap::new(),
+            },
             ..Default::default()
         }
     }
 
+    pub fn query(&self) {
+        match &self.source {
+            Some(DirectorySource::FindCommand(cmd)) => query_list(cmd),
+            Some(DirectorySource::Zoxide) => zoxide::query(),
+            None => {}
+        }
+    }
+
     pub fn load_cache(&mut self) {
-        let res = std::fs::read_to_string("/cache/store");
+        let path = match &self.source {
+            Some(source) => source.cache_path(),
+            None => return,
+        };
+        let res = std::fs::read_to_string(path);
 
         tracing::debug!("cache {:?}", res);
 
         if let Ok(res) = res {
-            self.list = res
-                .split("\n")
-                .map(|s| s.to_owned())
-                .collect::<Vec<String>>();
-
-            self.filter("");
+            self.update_from_output(&res);
         }
     }
 
     pub fn save_cache(&mut self) {
-        let _ = std::fs::write("/cache/store", self.list.join("\n"));
+        let path = match &self.source {
+            Some(source) => source.cache_path(),
+            None => return,
+        };
+
+        let content = match self.source {
+            Some(DirectorySource::Zoxide) => self
+                .list
+                .iter()
+                .map(|p| format!("{} {}", self.frecency_of(p), p))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            _ => self.list.join("\n"),
+        };
+
+        let _ = std::fs::write(path, content);
+    }
+
+    /// Replace the list with the output of the configured directory source.
+    pub fn update_from_output(&mut self, output: &str) {
+        match self.source {
+            Some(DirectorySource::Zoxide) => {
+                let scores = zoxide::parse_scores(output);
+
+                self.list = scores.iter().map(|(path, _)| path.to_owned()).collect();
+                self.frecency = scores.into_iter().collect();
+            }
+            _ => {
+                self.list = output
+                    .split('\n')
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.to_owned())
+                    .collect::<Vec<String>>();
+            }
+        }
+
+        self.filter(&self.search_query.clone());
     }
 
     pub fn update_list(&mut self, list: Vec<String>) {
@@ -114,43 +298,546 @@ impl NewSessionList {
         self.filter(&self.search_query.clone());
     }
 
-    pub fn update_sessions(&mut self, sessions: Vec<SessionInfo>) {
+    /// Store the result of a background git status query for a directory.
+    /// Directories, that are no git repository, are removed from the cache.
+    pub fn update_git_status(&mut self, path: &str, exit_code: Option<i32>, output: &str) {
+        match exit_code {
+            Some(0) => {
+                self.git_status
+                    .insert(path.to_owned(), GitStatus::parse(output));
+            }
+            _ => {
+                self.git_status.remove(path);
+            }
+        }
+
+        git::save_cache(&self.git_status);
+    }
+
+    /// Request the git status for a directory once per plugin run.
+    fn request_git_status(&mut self, path: &str) {
+        if !self.show_git_status || self.git_status_requested.contains(path) {
+            return;
+        }
+
+        git::query_status(path);
+        self.git_status_requested.insert(path.to_owned());
+    }
+
+    pub fn update_preview(&mut self, path: &str, exit_code: Option<i32>, output: &str) {
+        let preview = match exit_code {
+            Some(0) => output.to_owned(),
+            _ => "No preview available".to_owned(),
+        };
+
+        self.previews.insert(path.to_owned(), preview);
+    }
+
+    /// Details of the selected item: the running session or, for new
+    /// sessions, the README or files of the directory.
+    pub fn preview(&mut self) -> Vec<Text> {
+        let (name, cwd) = match self.selected_session() {
+            Some(session) => session,
+            None => return vec![],
+        };
+
+        if let Some(session) = self.session_list.iter().find(|s| s.name == name) {
+            return preview::session(session, self.metadata.get(&name));
+        }
+
+        if !self.previews_requested.contains(&cwd) {
+            preview::query(&cwd);
+            self.previews_requested.insert(cwd.clone());
+        }
+
+        preview::directory(self.previews.get(&cwd))
+    }
+
+    /// Store the manifest of a directory. Directories without a manifest
+    /// are remembered as well, so that they are not queried again.
+    pub fn update_manifest(
+        &mut self,
+        path: &str,
+        exit_code: Option<i32>,
+        output: &str,
+    ) -> Result<(), String> {
+        let manifest = match exit_code {
+            Some(0) => Manifest::parse(output),
+            _ => Ok(Manifest::default()),
+        };
+
+        match manifest {
+            Ok(mut manifest) => {
+                manifest.has_hook_file = manifest::has_hook_file(output);
+                self.manifests.insert(path.to_owned(), Some(manifest));
+                Ok(())
+            }
+            Err(e) => {
+                self.manifests.insert(path.to_owned(), None);
+                Err(e)
+            }
+        }
+    }
+
+    /// Request the manifest for a directory once per plugin run.
+    fn request_manifest(&mut self, path: &str) {
+        if self.manifests_requested.contains(path) {
+            return;
+        }
+
+        manifest::query(path, &path::session_name(path), false);
+        self.manifests_requested.insert(path.to_owned());
+    }
+
+    fn manifest(&self, path: &str) -> Option<&Manifest> {
+        self.manifests
+            .get(path)
+            .and_then(|manifest| manifest.as_ref())
+    }
+
+    /// Session name for a directory. The name from the manifest takes
+    /// precedence over the one derived from the path.
+    fn session_name(&self, path: &str) -> String {
+        self.manifest(path)
+            .and_then(|manifest| manifest.name.clone())
+            .unwrap_or_else(|| path::session_name(path))
+    }
+
+    /// Absolute path of a list entry.
+    fn resolve(&self, item: &str) -> String {
+        path::resolve(item, self.base_path.as_deref(), self.home_dir.as_deref())
+    }
+
+    /// Shortened path of a list entry, that is shown and matched against.
+    fn display_name(&self, item: &str) -> String {
+        path::shorten(
+            &self.resolve(item),
+            self.base_path.as_deref(),
+            self.home_dir.as_deref(),
+        )
+    }
+
+    /// Frecency scores of the directories by path.
+    pub fn frecency(&self) -> &BTreeMap<String, f64> {
+        &self.frecency
+    }
+
+    fn frecency_of(&self, item: &str) -> f64 {
+        self.frecency.get(item).copied().unwrap_or(0.0)
+    }
+
+    /// Boost for fuzzy matching, so often visited directories win over
+    /// equally good matches.
+    fn frecency_boost(&self, item: &str) -> i64 {
+        (self.frecency_of(item).ln_1p() * 4.0) as i64
+    }
+
+    pub fn update_sessions(&mut self, sessions: Vec<SessionInfo>, metadata: &Metadata) {
         self.session_list = sessions;
+        self.metadata = metadata.clone();
     }
 
     pub fn has_list(&mut self) -> bool {
         !self.list.is_empty() && !self.session_list.is_empty()
     }
 
-    pub fn create_or_attach(&mut self) {
-        let item = self
-            .filtered_list
-            .get(self.selected_item_index)
-            .unwrap()
-            .0
-            .strip_suffix("/")
-            .unwrap();
+    /// Expand the selected directory into its git worktrees. They are
+    /// listed in the background and shown as soon as the result arrives.
+    pub fn expand(&mut self) {
+        let cwd = match self.selected_path() {
+            Some(cwd) => cwd,
+            None => return,
+        };
+
+        if !self.worktrees.contains_key(&cwd) {
+            git::query_worktrees(&cwd);
+        }
 
-        let name = item.split("/").last().unwrap().replace(".", "_");
+        self.is_expanded = true;
+        self.selected_worktree_index = 0;
+    }
+
+    pub fn shrink(&mut self) {
+        self.is_expanded = false;
+    }
 
-        let cwd = match self.base_path.clone() {
-            Some(base_path) => format!("{base_path}/{item}"),
-            None => item.to_owned(),
+    pub fn update_worktrees(&mut self, path: &str, exit_code: Option<i32>, output: &str) {
+        let worktrees = match exit_code {
+            Some(0) => git::parse_worktrees(output),
+            _ => vec![],
         };
 
-        switch_session_with_cwd(Some(&name), Some(cwd.into()));
+        self.worktrees.insert(path.to_owned(), worktrees);
     }
 
-    pub fn delete_selected(&mut self) {
-        let item = self
-            .filtered_list
+    /// Start creating a new worktree for `branch` in the selected repository.
+    /// It is placed in the configured `worktree_path` or next to the
+    /// repository. Returns the path of the new worktree.
+    pub fn add_worktree(&mut self, branch: &str) -> Result<String, String> {
+        git::check_branch_name(branch)?;
+
+        let repo = self
+            .selected_path()
+            .ok_or_else(|| "No repository selected".to_owned())?;
+
+        let name = git::worktree_session_name(&self.session_name(&repo), branch);
+
+        let parent = match &self.worktree_path {
+            Some(worktree_path) => worktree_path.trim_end_matches('/').to_owned(),
+            None => match repo.rsplit_once('/') {
+                Some((parent, _)) => parent.to_owned(),
+                None => ".".to_owned(),
+            },
+        };
+        let worktree_path = format!(
+            "{parent}/{}",
+            git::worktree_session_name(&path::session_name(&repo), branch)
+        );
+
+        git::add_worktree(&repo, branch, &worktree_path, &name, false);
+        self.worktrees.remove(&repo);
+
+        Ok(worktree_path)
+    }
+
+    fn sort_key(&self, item: &str) -> SortKey {
+        let name = self.session_name(&self.resolve(item));
+        let session = self.session_list.iter().find(|s| s.name == name);
+
+        SortKey::session(
+            &self.display_name(item),
+            session,
+            self.metadata.get(&name),
+            self.frecency_of(item),
+        )
+    }
+
+    /// Configuration values in effect for the directory list, with the
+    /// defaults resolved.
+    pub fn settings(&self) -> Vec<(&'static str, String)> {
+        let or = |value: &Option<String>, default: &str| {
+            value.clone().unwrap_or_else(|| default.to_owned())
+        };
+        let source = match &self.source {
+            Some(DirectorySource::Zoxide) => "zoxide".to_owned(),
+            Some(DirectorySource::FindCommand(command)) => format!("find_command {command}"),
+            None => "none".to_owned(),
+        };
+
+        vec![
+            ("source", source),
+            ("base_path", or(&self.base_path, "none")),
+            ("home_dir", or(&self.home_dir, "none")),
+            ("session_format", self.format.template().to_owned()),
+            ("sort", self.sort.label().to_owned()),
+            ("git_status", self.show_git_status.to_string()),
+            (
+                "worktree_path",
+                or(&self.worktree_path, "next to the repository"),
+            ),
+            ("clone_path", or(&self.clone_path, "none")),
+            ("clone_host", self.clone_host.clone()),
+            ("scratch_path", or(&self.scratch_path, "none")),
+            ("scratch_init", or(&self.scratch_init, "none")),
+            ("post_create", or(&self.post_create, "none")),
+            ("project_hooks", self.project_hooks.to_string()),
+        ]
+    }
+
+    pub fn sort_order(&self) -> SortOrder {
+        self.sort
+    }
+
+    /// Switch to the next sort order. The selected directory stays
+    /// selected.
+    pub fn cycle_sort(&mut self) {
+        self.sort = self.sort.next();
+
+        let selected = self.selected_path();
+        self.filter(&self.search_query.clone());
+
+        let index = selected.and_then(|path| {
+            self.filtered_list
+                .iter()
+                .position(|(item, _)| self.resolve(item) == path)
+        });
+        if let Some(index) = index {
+            self.selected_item_index = index;
+        }
+    }
+
+    fn selected_path(&self) -> Option<String> {
+        self.filtered_list
             .get(self.selected_item_index)
-            .unwrap()
-            .0
-            .strip_suffix("/")
-            .unwrap();
+            .map(|(item, _)| self.resolve(item))
+    }
 
-        let name = item.split("/").last().unwrap().replace(".", "_");
+    /// Worktrees of the selected directory, when it is expanded.
+    fn selected_worktrees(&self) -> Option<&Vec<Worktree>> {
+        if !self.is_expanded {
+            return None;
+        }
+
+        self.worktrees
+            .get(&self.selected_path()?)
+            .filter(|worktrees| !worktrees.is_empty())
+    }
+
+    /// Cwd of the selected directory or worktree. Worktrees are named after
+    /// the session of their repository, while directories are only named,
+    /// once their manifest was read.
+    fn selected_target(&self) -> Option<(Option<String>, String)> {
+        let cwd = self.selected_path()?;
+
+        let worktree = self
+            .selected_worktrees()
+            .and_then(|worktrees| worktrees.get(self.selected_worktree_index));
+
+        match worktree {
+            Some(worktree) if worktree.path != cwd => Some((
+                Some(worktree.session_name(&self.session_name(&cwd))),
+                worktree.path.to_owned(),
+            )),
+            _ => Some((None, cwd)),
+        }
+    }
+
+    /// Session name and cwd of the selected directory or worktree.
+    fn selected_session(&self) -> Option<(String, String)> {
+        let (name, cwd) = self.selected_target()?;
+
+        Some((name.unwrap_or_else(|| self.session_name(&cwd)), cwd))
+    }
+
+    /// Add a directory, that was created by the plugin, to the list and the
+    /// cache.
+    pub fn add_entry(&mut self, path: &str) {
+        if self.source == Some(DirectorySource::Zoxide) {
+            zoxide::add(path);
+        }
+
+        if !self.list.iter().any(|item| self.resolve(item) == path) {
+            self.list.push(path.to_owned());
+            self.save_cache();
+        }
+
+        self.filter(&self.search_query.clone());
+    }
+
+    /// Run the configured init hook for a newly created project. Returns
+    /// true, when the hook was started and the session should only be
+    /// opened after it finished.
+    pub fn init_scratch(&mut self, path: &str, name: &str) -> bool {
+        match &self.scratch_init {
+            Some(hook) => {
+                scratch::init(hook, path, name);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn toggle_pin_selected(&mut self) {
+        let path = match self.selected_path() {
+            Some(path) => path,
+            None => return,
+        };
+
+        self.pins.toggle(&path);
+        self.filter(&self.search_query.clone());
+
+        if let Some(index) = self
+            .filtered_list
+            .iter()
+            .position(|(item, _)| self.resolve(item) == path)
+        {
+            self.selected_item_index = index;
+        }
+    }
+
+    fn selected_action(&self) -> Option<&ListAction> {
+        self.actions.get(
+            self.selected_item_index
+                .checked_sub(self.filtered_list.len())?,
+        )
+    }
+
+    fn row_count(&self) -> usize {
+        self.filtered_list.len() + self.actions.len()
+    }
+
+    /// Switch to the selected directory. When a pseudo row is selected, its
+    /// background command is started instead and a progress message is
+    /// returned. The session is opened once the command finished.
+    pub fn create_or_attach(&mut self) -> Result<Option<String>, String> {
+        if let Some(action) = self.selected_action() {
+            return match action {
+                ListAction::Clone { url, path } => {
+                    git::clone(url, path, &path::session_name(path));
+
+                    Ok(Some(format!("Cloning {url} into {path}...")))
+                }
+                ListAction::CreateDirectory { path } => {
+                    scratch::create(path, &path::session_name(path));
+
+                    Ok(Some(format!("Creating {path}...")))
+                }
+            };
+        }
+
+        let (name, cwd) = match self.selected_target() {
+            Some(target) => target,
+            None => return Ok(None),
+        };
+
+        if self.source == Some(DirectorySource::Zoxide) {
+            zoxide::add(&cwd);
+        }
+
+        self.open(name.as_deref(), &cwd)
+    }
+
+    /// Switch to the session of a path or create it, like for a selected
+    /// directory. Relative paths are resolved against the `base_path`.
+    pub fn create_or_attach_path(&mut self, item: &str) -> Result<Option<String>, String> {
+        let cwd = self.resolve(item);
+
+        if self.source == Some(DirectorySource::Zoxide) {
+            zoxide::add(&cwd);
+        }
+
+        self.open(None, &cwd)
+    }
+
+    /// Switch to a running session or open the directory of the list, whose
+    /// session has the name.
+    pub fn attach(&mut self, name: &str) -> Result<Option<String>, String> {
+        if !self.is_new_session(name) {
+            history::visit(name);
+            switch_session_with_focus(name, None, None);
+
+            return Ok(None);
+        }
+
+        let cwd = self
+            .list
+            .iter()
+            .map(|item| self.resolve(item))
+            .find(|cwd| self.session_name(cwd) == name)
+            .ok_or_else(|| format!("no session or directory named {name}"))?;
+
+        self.open(Some(name), &cwd)
+    }
+
+    /// Switch to the session or create it in `cwd`. Without a name, the
+    /// session is named after the directory or its manifest. When the
+    /// manifest of the directory is not known yet, it is read first and a
+    /// progress message is returned.
+    pub fn open(&mut self, name: Option<&str>, cwd: &str) -> Result<Option<String>, String> {
+        if !self.manifests.contains_key(cwd) {
+            manifest::query(cwd, name.unwrap_or_default(), true);
+
+            return Ok(Some(format!("Reading {} in {cwd}...", manifest::FILE_NAME)));
+        }
+
+        let name = name
+            .map(str::to_owned)
+            .unwrap_or_else(|| self.session_name(cwd));
+
+        self.switch(&name, cwd)?;
+
+        Ok(None)
+    }
+
+    /// Switch to the session. New sessions are started with the layout from
+    /// the manifest of their directory. Post-create hooks can't run in a
+    /// layout from the manifest, so they are skipped with an error, after
+    /// the session was opened.
+    fn switch(&self, name: &str, cwd: &str) -> Result<(), String> {
+        let available_layouts = self
+            .session_list
+            .iter()
+            .find(|s| s.is_current_session)
+            .map(|s| s.available_layouts.clone())
+            .unwrap_or_default();
+
+        if let Some(manifest) = self.manifest(cwd).filter(|m| !m.tags.is_empty()) {
+            let mut tags = Tags::load();
+            let mut session_tags = tags.get(name).cloned().unwrap_or_default();
+            session_tags.extend(manifest.tags.iter().cloned());
+
+            tags.set(name, session_tags);
+            tags.save();
+        }
+
+        let manifest = self.manifest(cwd).cloned().unwrap_or_default();
+        let hooks = match self.is_new_session(name) {
+            true => hooks::post_create(
+                name,
+                self.post_create.as_deref(),
+                self.project_hooks && manifest.has_hook_file,
+            ),
+            false => None,
+        };
+        let layout = match self.is_new_session(name) {
+            true => manifest.layout_info(cwd, &available_layouts, hooks.as_deref()),
+            false => None,
+        };
+
+        history::visit(name);
+        metadata::set_cwd(name, cwd);
+
+        match layout {
+            Some(layout) => switch_session_with_layout(Some(name), layout, Some(cwd.into())),
+            None => switch_session_with_cwd(Some(name), Some(cwd.into())),
+        }
+
+        match (&manifest.layout, hooks) {
+            (Some(layout), Some(_)) => Err(format!(
+                "Post-create hooks of {name} were skipped, since {} sets the layout {layout}",
+                manifest::FILE_NAME
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn is_new_session(&self, name: &str) -> bool {
+        !self.session_list.iter().any(|s| s.name == name)
+    }
+
+    fn update_actions(&mut self) {
+        self.actions = vec![];
+
+        if let Some(url) = git::clone_url(&self.search_query, &self.clone_host) {
+            if let Some(clone_path) = &self.clone_path {
+                let path = format!(
+                    "{}/{}",
+                    clone_path.trim_end_matches('/'),
+                    git::clone_directory_name(&url)
+                );
+
+                self.actions.push(ListAction::Clone { url, path });
+            }
+        }
+
+        // Offer to create a new project, when the search matches nothing.
+        let name = scratch::directory_name(&self.search_query);
+        if !self.filtered_list.is_empty() || name.is_empty() {
+            return;
+        }
+
+        if let Some(scratch_path) = &self.scratch_path {
+            let path = format!("{}/{}", scratch_path.trim_end_matches('/'), name);
+
+            self.actions.push(ListAction::CreateDirectory { path });
+        }
+    }
+
+    pub fn delete_selected(&mut self) {
+        let name = match self.selected_session() {
+            Some((name, _)) => name,
+            None => return,
+        };
 
         tracing::debug!("delete {}", name);
 
@@ -161,44 +848,85 @@ impl NewSessionList {
 
     pub fn filter(&mut self, search_query: &str) {
         tracing::debug!("filter");
-        if self.list.is_empty() {
-            return;
+        if self.search_query != search_query {
+            self.is_expanded = false;
         }
 
         self.search_query = search_query.to_owned();
 
         if search_query.is_empty() {
-            self.filtered_list = self.list.iter().map(|i| (i.to_owned(), vec![])).collect();
-            self.filtered_list_len = self.filtered_list.len();
-            return;
-        }
+            let mut list = self.list.clone();
+            list.sort_by(|a, b| self.frecency_of(b).total_cmp(&self.frecency_of(a)));
 
-        let mut list = self
-            .list
-            .iter()
-            .flat_map(|f| {
-                self.matcher
-                    .fuzzy_indices(f, search_query)
-                    .map(|res| (f.to_owned(), res))
-            })
-            .collect::<Vec<(String, (i64, Vec<usize>))>>();
+            self.filtered_list = list.into_iter().map(|i| (i, vec![])).collect();
+        } else {
+            let mut list = self
+                .list
+                .iter()
+                .flat_map(|f| {
+                    self.matcher
+                        .fuzzy_indices(&self.display_name(f), search_query)
+                        .map(|(score, indices)| {
+                            (f.to_owned(), (score + self.frecency_boost(f), indices))
+                        })
+                })
+                .collect::<Vec<(String, (i64, Vec<usize>))>>();
 
-        list.sort_by_key(|i| std::cmp::Reverse(i.1 .0));
+            list.sort_by_key(|i| std::cmp::Reverse(i.1 .0));
 
-        self.filtered_list = list
-            .iter()
-            .map(|item| (item.0.to_owned(), item.1 .1.clone()))
-            .collect::<Vec<(String, Vec<usize>)>>();
+            self.filtered_list = list
+                .iter()
+                .map(|item| (item.0.to_owned(), item.1 .1.clone()))
+                .collect::<Vec<(String, Vec<usize>)>>();
+        }
+
+        // Pinned directories stay on top, regardless of the sort order.
+        let mut filtered_list = std::mem::take(&mut self.filtered_list);
+        self.sort
+            .sort(&mut filtered_list, |(item, _)| self.sort_key(item));
+        filtered_list.sort_by_key(|(item, _)| !self.pins.contains(&self.resolve(item)));
+        self.filtered_list = filtered_list;
 
         self.filtered_list_len = self.filtered_list.len();
+        self.update_actions();
 
-        if self.selected_item_index > self.filtered_list.len() {
-            self.selected_item_index = self.filtered_list.len();
+        if self.selected_item_index >= self.row_count() {
+            self.selected_item_index = self.row_count().saturating_sub(1);
         }
     }
 
+    /// Open the directory with the hint at `index` in the visible window,
+    /// without changing the selection. Like `open`, a progress message is
+    /// returned, while the session is not opened yet.
+    pub fn attach_index(&mut self, index: usize) -> Result<Option<String>, String> {
+        let item = self
+            .filtered_list
+            .get(self.offset + index)
+            .map(|(item, _)| item.to_owned())
+            .ok_or_else(|| "no directory for the hint".to_owned())?;
+
+        self.create_or_attach_path(&item)
+    }
+
+    /// Select another directory. Its worktrees are not expanded.
+    fn select_item(&mut self, index: usize) {
+        if index != self.selected_item_index {
+            self.is_expanded = false;
+        }
+
+        self.selected_item_index = index;
+    }
+
     pub fn select_next(&mut self) {
-        if self.list.is_empty() {
+        if self.row_count() == 0 {
+            return;
+        }
+
+        if let Some(worktrees) = self.selected_worktrees() {
+            self.selected_worktree_index = (self.selected_worktree_index as i32 + 1)
+                .rem_euclid(worktrees.len() as i32)
+                as usize;
+
             return;
         }
         tracing::debug!(
@@ -208,38 +936,97 @@ impl NewSessionList {
             self.max_items
         );
 
-        if self.selected_item_index >= self.filtered_list.len() - 1 {
-            self.selected_item_index = self.filtered_list.len() - 1;
+        let index = std::cmp::min(self.selected_item_index + 1, self.row_count() - 1);
+        self.select_item(index);
+    }
+
+    pub fn select_prev(&mut self) {
+        if self.row_count() == 0 {
             return;
         }
 
-        if let Some(max) = self.max_items {
-            self.selected_item_index = std::cmp::min(self.selected_item_index + 1, max);
+        if let Some(worktrees) = self.selected_worktrees() {
+            self.selected_worktree_index = (self.selected_worktree_index as i32 - 1)
+                .rem_euclid(worktrees.len() as i32)
+                as usize;
+
             return;
         }
 
-        self.selected_item_index = (self.selected_item_index as i32 + 1)
-            .rem_euclid(self.filtered_list_len as i32) as usize;
+        self.select_item(self.selected_item_index.saturating_sub(1));
     }
 
-    pub fn select_prev(&mut self) {
-        if self.list.is_empty() {
-            return;
+    /// Rows of the selected directory, including its expanded worktrees.
+    fn selected_rows(&self) -> usize {
+        1 + self.selected_worktrees().map(Vec::len).unwrap_or_default()
+    }
+
+    /// Scroll the window just enough to show the selected directory and its
+    /// worktrees, as far as they fit.
+    fn scroll(&mut self, height: usize) {
+        let rows = self.selected_rows();
+
+        if self.selected_item_index < self.offset {
+            self.offset = self.selected_item_index;
+        } else if self.selected_item_index + rows.min(height) > self.offset + height {
+            self.offset = self.selected_item_index + rows.min(height) - height;
         }
 
-        self.selected_item_index = self.selected_item_index.saturating_sub(1);
+        self.offset = self
+            .offset
+            .min((self.row_count() + rows - 1).saturating_sub(height));
     }
 
-    fn list_window(&mut self, height: usize) -> Vec<(String, Vec<usize>)> {
-        if self.filtered_list.is_empty() {
-            return vec![];
+    /// Row and worktree, that are shown in the row of the rendered list.
+    fn row_at(&self, row: usize) -> Option<(usize, Option<usize>)> {
+        let mut rows = 0;
+
+        for index in self.offset..self.row_count() {
+            let len = match index == self.selected_item_index {
+                true => self.selected_rows(),
+                false => 1,
+            };
+
+            if row < rows + len {
+                return Some((index, (row - rows).checked_sub(1)));
+            }
+            rows += len;
         }
 
-        if self.filtered_list.len() <= height {
-            return self.filtered_list.clone();
+        None
+    }
+
+    /// Select the directory, worktree or action in the row of the rendered
+    /// list. Clicking the directory of expanded worktrees folds them.
+    /// Returns true for a double click, that should attach to the row.
+    pub fn click(&mut self, row: usize) -> bool {
+        if row >= self.max_items.unwrap_or_default() {
+            return false;
         }
 
-        self.filtered_list[0..=height].to_owned()
+        let target = match self.row_at(row) {
+            Some(target) => target,
+            None => return false,
+        };
+
+        let is_double_click = self
+            .last_click
+            .as_ref()
+            .is_some_and(|(last, time)| *last == target && time.elapsed() < DOUBLE_CLICK);
+        self.last_click = match is_double_click {
+            true => None,
+            false => Some((target, Instant::now())),
+        };
+
+        match target {
+            (_, Some(worktree)) => self.selected_worktree_index = worktree,
+            (index, None) => {
+                self.select_item(index);
+                self.shrink();
+            }
+        }
+
+        is_double_click
     }
 
     pub fn get_list(&mut self, height: usize) -> Vec<NestedListItem> {
@@ -247,48 +1034,287 @@ impl NewSessionList {
 
         let mut output: Vec<NestedListItem> = vec![];
 
-        let list_window = self.list_window(height);
+        self.scroll(height);
+        let offset = self.offset;
         tracing::debug!("selected {}", self.selected_item_index);
 
-        for (index, match_name) in list_window.into_iter().enumerate() {
-            let (match_name, indice) = match_name.clone();
-            let name = match_name
-                .strip_suffix("/")
-                .unwrap_or(&match_name)
-                .split("/")
-                .last()
-                .unwrap()
-                .replace(".", "_");
-
-            tracing::debug!("name {}", name);
-
-            let mut item = NestedListItem::new(&match_name)
-                .color_range(0, 0..match_name.len())
-                .color_indices(1, indice);
-
-            if !self.session_list.is_empty() {
-                if let Some(session) = self.session_list.iter().find(|s| s.name == name.clone()) {
-                    item = NestedListItem::new(format!(
-                        "{} ({} tabs, {} panes) [{} connected users]",
-                        &match_name,
-                        session.tabs.len(),
-                        session.panes.panes.len(),
-                        session.connected_clients,
-                    ))
-                    .color_range(0, 0..match_name.len())
-                    .color_range(1, session.name.len() + 2..session.name.len() + 3)
-                    .color_range(2, session.name.len() + 10..session.name.len() + 11)
-                    .color_range(0, session.name.len() + 20..session.name.len() + 21);
+        for index in offset..self.row_count() {
+            if output.len() >= height {
+                break;
+            }
+
+            let is_selected = index == self.selected_item_index;
+
+            match self.filtered_list.get(index).cloned() {
+                Some((item, indices)) => {
+                    let hint = index - offset;
+                    output.append(&mut self.directory_rows(hint, &item, indices, is_selected));
+                }
+                None => {
+                    let action = &self.actions[index - self.filtered_list.len()];
+                    let label = action.label();
+
+                    let mut item = NestedListItem::new(&label).color_range(2, ..);
+                    if is_selected {
+                        item = item.selected();
+                    }
+
+                    output.push(item);
                 }
             }
+        }
+
+        output.truncate(height);
+        output
+    }
+
+    fn directory_rows(
+        &mut self,
+        hint: usize,
+        item: &str,
+        indices: Vec<usize>,
+        is_selected: bool,
+    ) -> Vec<NestedListItem> {
+        let mut output: Vec<NestedListItem> = vec![];
 
-            if index == std::cmp::min(self.selected_item_index, height) {
-                item = item.selected();
+        let match_name = self.display_name(item);
+        let cwd = self.resolve(item);
+        self.request_git_status(&cwd);
+        self.request_manifest(&cwd);
+
+        let name = self.session_name(&cwd);
+
+        tracing::debug!("name {}", name);
+
+        let session = self.session_list.iter().find(|s| s.name == name);
+
+        let mut row = RowText::new();
+        row.push(&hints::prefix(hint), Some(3));
+
+        let name_range = match session {
+            Some(session) => self.format.render(
+                &mut row,
+                &format::session_values(&match_name, session, self.metadata.get(&session.name)),
+            ),
+            None => Some(row.push(&match_name, Some(0))),
+        };
+
+        if let Some(status) = self.git_status.get(&cwd) {
+            row.push(" [", None);
+            row.push(&status.summary(), Some(3));
+            row.push("]", None);
+        }
+
+        if self.pins.contains(&cwd) {
+            row.push(" ", None);
+            row.push("★", Some(2));
+        }
+
+        let mut item = row.item();
+        if let Some(name_range) = name_range {
+            let indices = indices.iter().map(|i| i + name_range.start).collect();
+            item = item.color_indices(1, indices);
+        }
+
+        let worktrees = match is_selected {
+            true => self.selected_worktrees().cloned().unwrap_or_default(),
+            false => vec![],
+        };
+
+        if is_selected && worktrees.is_empty() {
+            item = item.selected();
+        }
+
+        output.push(item);
+
+        for (worktree_index, worktree) in worktrees.iter().enumerate() {
+            let label = worktree.label();
+            let mut worktree_item = NestedListItem::new(format!(
+                "{} ({})",
+                label,
+                path::shorten(
+                    &worktree.path,
+                    self.base_path.as_deref(),
+                    self.home_dir.as_deref()
+                ),
+            ))
+            .color_range(1, 0..label.len())
+            .indent(1);
+
+            if worktree_index == self.selected_worktree_index {
+                worktree_item = worktree_item.selected();
             }
 
-            output.push(item);
+            output.push(worktree_item);
         }
 
         output
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list_with(items: &[&str]) -> NewSessionList {
+        let mut list = NewSessionList::default();
+        list.update_list(items.iter().map(|item| item.to_string()).collect());
+
+        list
+    }
+
+    /// Scroll like `get_list`, which also starts background commands for
+    /// the visible directories.
+    fn render(list: &mut NewSessionList, height: usize) {
+        list.max_items = Some(height);
+        list.scroll(height);
+    }
+
+    fn worktree(path: &str, branch: &str) -> Worktree {
+        Worktree {
+            path: path.to_owned(),
+            branch: Some(branch.to_owned()),
+        }
+    }
+
+    #[test]
+    fn worktrees_are_named_after_the_repository_manifest() {
+        let mut list = list_with(&["/src/api-repo"]);
+        let manifest = Manifest {
+            name: Some("api".to_owned()),
+            ..Default::default()
+        };
+        list.manifests
+            .insert("/src/api-repo".to_owned(), Some(manifest));
+        list.worktrees.insert(
+            "/src/api-repo".to_owned(),
+            vec![
+                worktree("/src/api-repo", "main"),
+                worktree("/src/api-repo_login", "feature/login"),
+            ],
+        );
+        list.is_expanded = true;
+
+        assert_eq!(
+            list.selected_target(),
+            Some((None, "/src/api-repo".to_owned()))
+        );
+        assert_eq!(list.session_name("/src/api-repo"), "api");
+
+        list.select_next();
+        assert_eq!(
+            list.selected_target(),
+            Some((
+                Some("api_feature_login".to_owned()),
+                "/src/api-repo_login".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn manifests_report_hook_files() {
+        let mut list = list_with(&["/src/api", "/src/web"]);
+
+        list.update_manifest("/src/api", Some(0), "// .zj-session\nname \"api\"\n")
+            .unwrap();
+        list.update_manifest("/src/web", Some(1), "").unwrap();
+
+        let manifest = list.manifest("/src/api").unwrap();
+        assert!(manifest.has_hook_file);
+        assert_eq!(manifest.name.as_deref(), Some("api"));
+        assert!(!list.manifest("/src/web").unwrap().has_hook_file);
+    }
+
+    #[test]
+    fn clicks_select_rows_of_the_window() {
+        let mut list = list_with(&["/src/a", "/src/b", "/src/c", "/src/d", "/src/e"]);
+        for _ in 0..3 {
+            list.select_next();
+        }
+        render(&mut list, 3);
+
+        assert_eq!(list.offset, 1);
+        assert_eq!(list.row_at(0), Some((1, None)));
+        assert_eq!(list.row_at(2), Some((3, None)));
+
+        assert!(!list.click(0));
+        assert_eq!(list.selected_item_index, 1);
+        assert!(list.click(0));
+
+        assert!(!list.click(2));
+        assert!(!list.click(1));
+        assert_eq!(list.selected_item_index, 2);
+        assert!(!list.click(3));
+        assert_eq!(list.selected_item_index, 2);
+    }
+
+    #[test]
+    fn clicks_select_worktrees() {
+        let mut list = list_with(&["/src/a", "/src/b"]);
+        list.worktrees.insert(
+            "/src/a".to_owned(),
+            vec![worktree("/src/a", "main"), worktree("/src/a_x", "x")],
+        );
+        list.is_expanded = true;
+        render(&mut list, 4);
+
+        assert_eq!(list.row_at(1), Some((0, Some(0))));
+        assert_eq!(list.row_at(2), Some((0, Some(1))));
+        assert_eq!(list.row_at(3), Some((1, None)));
+        assert_eq!(list.row_at(4), None);
+
+        assert!(!list.click(2));
+        assert_eq!(
+            list.selected_target(),
+            Some((Some("a_x".to_owned()), "/src/a_x".to_owned()))
+        );
+        assert!(list.click(2));
+
+        assert!(!list.click(0));
+        assert!(!list.is_expanded);
+        assert_eq!(list.row_at(1), Some((1, None)));
+    }
+
+    #[test]
+    fn window_keeps_expanded_worktrees_of_the_last_row_visible() {
+        let mut list = list_with(&["/src/a", "/src/b", "/src/c"]);
+        list.worktrees.insert(
+            "/src/c".to_owned(),
+            vec![worktree("/src/c", "main"), worktree("/src/c_x", "x")],
+        );
+        list.select_next();
+        list.select_next();
+        list.is_expanded = true;
+        render(&mut list, 3);
+
+        assert_eq!(list.offset, 2);
+        assert_eq!(list.row_at(0), Some((2, None)));
+        assert_eq!(list.row_at(2), Some((2, Some(1))));
+
+        render(&mut list, 2);
+        assert_eq!(list.offset, 2);
+        assert_eq!(list.row_at(1), Some((2, Some(0))));
+    }
+
+    #[test]
+    fn settings_resolve_the_defaults() {
+        let config = vec![
+            ("home_dir".to_owned(), "/home/user".to_owned()),
+            ("source".to_owned(), "zoxide".to_owned()),
+        ]
+        .into_iter()
+        .collect();
+        let settings = NewSessionList::new(&config)
+            .settings()
+            .into_iter()
+            .collect::<BTreeMap<&str, String>>();
+
+        assert_eq!(settings["source"], "zoxide");
+        assert_eq!(settings["clone_path"], "/home/user");
+        assert_eq!(settings["scratch_path"], "/home/user");
+        assert_eq!(settings["worktree_path"], "next to the repository");
+        assert_eq!(settings["clone_host"], "github.com");
+        assert_eq!(settings["sort"], "score");
+        assert_eq!(settings["session_format"], format::DEFAULT_SESSION_FORMAT);
+    }
+}
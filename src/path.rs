@@ -0,0 +1,161 @@
+/// Expand a leading `~`, `$HOME` or `${HOME}` with the given home directory.
+/// Paths are returned unchanged, when no home directory is known.
+pub fn expand_home(path: &str, home_dir: Option<&str>) -> String {
+    let home_dir = match home_dir {
+        Some(home_dir) => home_dir.trim_end_matches('/'),
+        None => return path.to_owned(),
+    };
+
+    for prefix in ["${HOME}", "$HOME", "~"] {
+        if let Some(rest) = path.strip_prefix(prefix) {
+            if rest.is_empty() || rest.starts_with('/') {
+                return format!("{home_dir}{rest}");
+            }
+        }
+    }
+
+    path.to_owned()
+}
+
+/// Resolve an entry of the directory list to an absolute path without a
+/// trailing slash. Relative entries are joined with the base path, absolute
+/// and home relative entries are taken as they are.
+pub fn resolve(item: &str, base_path: Option<&str>, home_dir: Option<&str>) -> String {
+    let item = expand_home(item.trim(), home_dir);
+    let item = trim_trailing_slash(&item);
+
+    if item.starts_with('/') {
+        return item.to_owned();
+    }
+
+    match base_path {
+        Some(base_path) => {
+            let base_path = expand_home(base_path, home_dir);
+
+            format!("{}/{}", base_path.trim_end_matches('/'), item)
+        }
+        None => item.to_owned(),
+    }
+}
+
+/// Derive the session name from the last path component.
+pub fn session_name(path: &str) -> String {
+    trim_trailing_slash(path)
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .replace('.', "_")
+}
+
+/// Shorten a resolved path for displaying it. Paths below the base path are
+/// shown relative to it, paths below the home directory start with `~`.
+pub fn shorten(path: &str, base_path: Option<&str>, home_dir: Option<&str>) -> String {
+    if let Some(base_path) = base_path {
+        let base_path = expand_home(base_path, home_dir);
+
+        if let Some(rest) = strip_dir_prefix(path, &base_path) {
+            if !rest.is_empty() {
+                return rest.to_owned();
+            }
+        }
+    }
+
+    if let Some(home_dir) = home_dir {
+        if let Some(rest) = strip_dir_prefix(path, home_dir) {
+            return match rest.is_empty() {
+                true => "~".to_owned(),
+                false => format!("~/{rest}"),
+            };
+        }
+    }
+
+    path.to_owned()
+}
+
+fn strip_dir_prefix<'a>(path: &'a str, dir: &str) -> Option<&'a str> {
+    let rest = path.strip_prefix(dir.trim_end_matches('/'))?;
+
+    if rest.is_empty() {
+        return Some(rest);
+    }
+
+    rest.strip_prefix('/')
+}
+
+fn trim_trailing_slash(path: &str) -> &str {
+    match path.trim_end_matches('/') {
+        "" if path.starts_with('/') => "/",
+        trimmed => trimmed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOME: Option<&str> = Some("/home/user");
+
+    #[test]
+    fn expand_home_prefixes() {
+        assert_eq!(expand_home("~", HOME), "/home/user");
+        assert_eq!(expand_home("~/code", HOME), "/home/user/code");
+        assert_eq!(expand_home("$HOME/code", HOME), "/home/user/code");
+        assert_eq!(expand_home("${HOME}/code", HOME), "/home/user/code");
+        assert_eq!(
+            expand_home("~/code", Some("/home/user/")),
+            "/home/user/code"
+        );
+    }
+
+    #[test]
+    fn expand_home_leaves_other_paths() {
+        assert_eq!(expand_home("~other/code", HOME), "~other/code");
+        assert_eq!(expand_home("$HOMEDIR/code", HOME), "$HOMEDIR/code");
+        assert_eq!(expand_home("/srv/~", HOME), "/srv/~");
+        assert_eq!(expand_home("~/code", None), "~/code");
+    }
+
+    #[test]
+    fn resolve_entries() {
+        let base = Some("/srv/projects");
+
+        assert_eq!(resolve("/opt/tool/", base, HOME), "/opt/tool");
+        assert_eq!(resolve("~/notes", base, HOME), "/home/user/notes");
+        assert_eq!(
+            resolve("rust/zellij", base, HOME),
+            "/srv/projects/rust/zellij"
+        );
+        assert_eq!(
+            resolve("rust/zellij/", base, HOME),
+            "/srv/projects/rust/zellij"
+        );
+        assert_eq!(resolve("rust/zellij", None, HOME), "rust/zellij");
+        assert_eq!(resolve("/", base, HOME), "/");
+    }
+
+    #[test]
+    fn resolve_home_in_base_path() {
+        assert_eq!(resolve("code", Some("$HOME/"), HOME), "/home/user/code");
+        assert_eq!(resolve("code", Some("~"), HOME), "/home/user/code");
+    }
+
+    #[test]
+    fn shorten_paths() {
+        let base = Some("~/projects");
+
+        assert_eq!(shorten("/home/user/projects/app", base, HOME), "app");
+        assert_eq!(shorten("/home/user/projects", base, HOME), "~/projects");
+        assert_eq!(shorten("/home/user/notes", base, HOME), "~/notes");
+        assert_eq!(shorten("/home/user", base, HOME), "~");
+        assert_eq!(shorten("/home/username", base, HOME), "/home/username");
+        assert_eq!(shorten("/srv/app", None, None), "/srv/app");
+    }
+
+    #[test]
+    fn session_names() {
+        assert_eq!(session_name("/home/user/app"), "app");
+        assert_eq!(session_name("/home/user/app/"), "app");
+        assert_eq!(session_name("/home/user/my.app.rs"), "my_app_rs");
+        assert_eq!(session_name("/"), "");
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::BTreeSet;
+
+/// Pinned sessions or directories, persisted in the plugin cache with one
+/// item per line.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Pins {
+    cache_path: &'static str,
+    items: BTreeSet<String>,
+}
+
+impl Pins {
+    pub fn sessions() -> Self {
+        Self::load("/cache/pinned_sessions")
+    }
+
+    pub fn directories() -> Self {
+        Self::load("/cache/pinned_directories")
+    }
+
+    fn load(cache_path: &'static str) -> Self {
+        let items = std::fs::read_to_string(cache_path)
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_owned())
+            .collect();
+
+        Self { cache_path, items }
+    }
+
+    fn save(&self) {
+        let content = self
+            .items
+            .iter()
+            .cloned()
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let _ = std::fs::write(self.cache_path, content);
+    }
+
+    pub fn contains(&self, item: &str) -> bool {
+        self.items.contains(item)
+    }
+
+    /// Pin or unpin the item and persist the change.
+    pub fn toggle(&mut self, item: &str) {
+        if !self.items.remove(item) {
+            self.items.insert(item.to_owned());
+        }
+
+        self.save();
+    }
+}
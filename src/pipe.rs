@@ -0,0 +1,85 @@
+/// Command sent to the plugin through `zellij pipe` or a `MessagePlugin`
+/// keybinding, e.g. `zellij pipe --plugin zj-smart-sessions -- attach api`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Attach to a session or open the directory with that session name.
+    Attach(String),
+    /// Attach to or create the session for a directory.
+    Create(String),
+    /// Print sessions, tabs, panes, tags and frecency scores as JSON.
+    List,
+}
+
+impl Command {
+    /// Parse a command from the pipe name and payload. The command can be
+    /// given as name (`--name attach -- api`) or as first word of the
+    /// payload (`-- attach api`).
+    pub fn parse(name: &str, payload: Option<&str>) -> Result<Self, String> {
+        let payload = payload.unwrap_or_default().trim();
+
+        let (command, argument) = match name {
+            "attach" | "create" | "list" => (name, payload),
+            _ => payload.split_once(' ').unwrap_or((payload, "")),
+        };
+        let argument = argument.trim();
+
+        match command {
+            "attach" | "create" if argument.is_empty() => {
+                Err(format!("missing argument for {command}"))
+            }
+            "attach" => Ok(Self::Attach(argument.to_owned())),
+            "create" => Ok(Self::Create(argument.to_owned())),
+            "list" => Ok(Self::List),
+            "" => Err("missing command".to_owned()),
+            _ => Err(format!("unknown command {command}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_in_name() {
+        assert_eq!(
+            Command::parse("attach", Some(" api ")),
+            Ok(Command::Attach("api".to_owned()))
+        );
+        assert_eq!(
+            Command::parse("create", Some("~/src/api")),
+            Ok(Command::Create("~/src/api".to_owned()))
+        );
+        assert_eq!(Command::parse("list", None), Ok(Command::List));
+    }
+
+    #[test]
+    fn command_in_payload() {
+        assert_eq!(
+            Command::parse("zj-smart-sessions", Some("attach  my session")),
+            Ok(Command::Attach("my session".to_owned()))
+        );
+        assert_eq!(Command::parse("", Some("list")), Ok(Command::List));
+    }
+
+    #[test]
+    fn invalid_commands() {
+        assert_eq!(
+            Command::parse("attach", None),
+            Err("missing argument for attach".to_owned())
+        );
+        assert_eq!(
+            Command::parse("", Some("create ")),
+            Err("missing argument for create".to_owned())
+        );
+        assert_eq!(Command::parse("", None), Err("missing command".to_owned()));
+        assert_eq!(
+            Command::parse("", Some("  ")),
+            Err("missing command".to_owned())
+        );
+        assert_eq!(
+            Command::parse("", Some("kill api")),
+            Err("unknown command kill".to_owned())
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+
+use zellij_tile::{
+    prelude::{PaneInfo, SessionInfo, TabInfo},
+    shim::{run_command_with_env_variables_and_cwd, Text},
+};
+
+use crate::{command, format::RowText, metadata::SessionMetadata};
+
+/// Columns needed to show the preview next to the list.
+pub const MIN_COLUMNS: usize = 100;
+
+/// Lines of a README shown in the preview of a directory.
+const README_LINES: usize = 40;
+
+const SCRIPT: &str = r#"for file in README.md README readme.md README.rst README.txt; do
+    if [ -f "$file" ]; then
+        exec head -n "$1" "$file"
+    fi
+done
+exec ls -Ap"#;
+
+/// Details of a session: the connected clients, its metadata and its tabs
+/// with their swap layouts and panes.
+pub fn session(session: &SessionInfo, metadata: Option<&SessionMetadata>) -> Vec<Text> {
+    let mut lines = vec![];
+
+    let mut header = RowText::new();
+    header.push(&session.name, Some(0));
+    header.push(
+        &format!(" ({} connected users)", session.connected_clients),
+        None,
+    );
+    lines.push(header.text());
+
+    if let Some(metadata) = metadata {
+        lines.push(Text::new(format!(
+            "created {} ago, changed {}",
+            metadata.age(),
+            metadata.activity()
+        )));
+
+        if let Some(cwd) = &metadata.cwd {
+            lines.push(Text::new(cwd).color_range(3, ..));
+        }
+    }
+    lines.push(Text::new(""));
+
+    for tab in &session.tabs {
+        let mut line = RowText::new();
+        line.push(&(tab.position + 1).to_string(), tab.active.then_some(1));
+        line.push(": ", None);
+        line.push(&tab.name, Some(2));
+
+        if let Some(layout) = &tab.active_swap_layout_name {
+            line.push(&format!(" [{}]", layout), None);
+        }
+        lines.push(line.text());
+
+        let panes = session
+            .panes
+            .panes
+            .get(&tab.position)
+            .map(|panes| panes.as_slice())
+            .unwrap_or_default();
+
+        for pane in panes.iter().filter(|pane| pane.is_selectable) {
+            let command = match pane.is_plugin {
+                true => pane.plugin_url.clone(),
+                false => pane.terminal_command.clone(),
+            };
+
+            let mut line = RowText::new();
+            line.push("  ", None);
+            line.push(&pane.title, pane.is_focused.then_some(3));
+
+            if let Some(command) = command.filter(|command| *command != pane.title) {
+                line.push(&format!(" ({})", command), None);
+            }
+            lines.push(line.text());
+        }
+    }
+
+    lines
+}
+
+/// Mini-map of the tiled panes of a tab, scaled to the preview. Every pane
+/// is drawn as box with its title; the focused pane is highlighted. Floating
+/// panes are listed below the map.
+pub fn tab(session: &SessionInfo, tab: &TabInfo, width: usize, height: usize) -> Vec<Text> {
+    let panes = session
+        .panes
+        .panes
+        .get(&tab.position)
+        .map(|panes| panes.as_slice())
+        .unwrap_or_default();
+
+    let selectable = panes
+        .iter()
+        .filter(|pane| pane.is_selectable && !pane.is_suppressed);
+    let (floating, tiled): (Vec<&PaneInfo>, Vec<&PaneInfo>) =
+        selectable.partition(|pane| pane.is_floating);
+
+    let mut title = RowText::new();
+    title.push(&format!("{}: {}", tab.position + 1, tab.name), Some(2));
+
+    let mut lines = vec![title.text(), Text::new("")];
+
+    let map_height = height.saturating_sub(lines.len() + floating.len() + 1);
+    lines.append(&mut mini_map(&tiled, width, map_height));
+
+    if !floating.is_empty() {
+        lines.push(Text::new(""));
+    }
+
+    for pane in floating {
+        let mut line = Text::new(format!("floating: {}", pane.title));
+        if pane.is_focused {
+            line = line.color_range(3, 10..10 + pane.title.chars().count());
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+fn mini_map(panes: &[&PaneInfo], width: usize, height: usize) -> Vec<Text> {
+    let left = panes.iter().map(|p| p.pane_x).min().unwrap_or_default();
+    let top = panes.iter().map(|p| p.pane_y).min().unwrap_or_default();
+    let right = panes.iter().map(|p| p.pane_x + p.pane_columns).max();
+    let bottom = panes.iter().map(|p| p.pane_y + p.pane_rows).max();
+
+    let (right, bottom) = match (right, bottom) {
+        (Some(right), Some(bottom)) if right > left && bottom > top => (right, bottom),
+        _ => return vec![],
+    };
+
+    if width < 3 || height < 3 {
+        return vec![];
+    }
+
+    // Panes share their borders, so that the right and bottom edges of the
+    // last panes end on the last column and row.
+    let scale_x = |x: usize| (x - left) * (width - 1) / (right - left);
+    let scale_y = |y: usize| (y - top) * (height - 1) / (bottom - top);
+
+    let mut grid = vec![vec![' '; width]; height];
+    let mut focused = None;
+
+    for pane in panes {
+        let x0 = scale_x(pane.pane_x);
+        let x1 = scale_x(pane.pane_x + pane.pane_columns);
+        let y0 = scale_y(pane.pane_y);
+        let y1 = scale_y(pane.pane_y + pane.pane_rows);
+
+        if x1 <= x0 || y1 <= y0 {
+            continue;
+        }
+
+        for y in [y0, y1] {
+            grid[y][x0..=x1].fill('─');
+        }
+        for row in grid.iter_mut().take(y1).skip(y0 + 1) {
+            row[x0] = '│';
+            row[x1] = '│';
+        }
+        grid[y0][x0] = '┌';
+        grid[y0][x1] = '┐';
+        grid[y1][x0] = '└';
+        grid[y1][x1] = '┘';
+
+        if y1 > y0 + 1 {
+            let title = pane.title.chars().take(x1 - x0 - 1);
+            for (cell, c) in grid[y0 + 1][x0 + 1..].iter_mut().zip(title) {
+                *cell = c;
+            }
+        }
+
+        if pane.is_focused {
+            focused = Some((x0..x1 + 1, y0..y1 + 1));
+        }
+    }
+
+    grid.into_iter()
+        .enumerate()
+        .map(|(y, row)| {
+            let line = Text::new(row.into_iter().collect::<String>());
+
+            match &focused {
+                Some((columns, rows)) if rows.contains(&y) => line.color_range(3, columns.clone()),
+                _ => line,
+            }
+        })
+        .collect()
+}
+
+/// Preview of a directory from the README or a listing of its files.
+pub fn directory(content: Option<&String>) -> Vec<Text> {
+    match content {
+        Some(content) => content.lines().map(Text::new).collect(),
+        None => vec![Text::new("Loading...")],
+    }
+}
+
+/// Read the README of a directory or list its files in the background.
+pub fn query(path: &str) {
+    let context = command::context("preview", &[("path", path)]);
+
+    run_command_with_env_variables_and_cwd(
+        &["sh", "-c", SCRIPT, "sh", &README_LINES.to_string()],
+        BTreeMap::new(),
+        path.into(),
+        context,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pane(title: &str, x: usize, columns: usize, y: usize, rows: usize) -> PaneInfo {
+        PaneInfo {
+            title: title.to_owned(),
+            pane_x: x,
+            pane_columns: columns,
+            pane_y: y,
+            pane_rows: rows,
+            ..Default::default()
+        }
+    }
+
+    /// Characters of the rendered lines. `Text` only exposes its content
+    /// serialized, as bytes after the color indices.
+    fn lines(texts: &[Text]) -> Vec<String> {
+        texts
+            .iter()
+            .map(|text| {
+                let serialized = text.serialize();
+                let bytes = serialized
+                    .rsplit('$')
+                    .next()
+                    .unwrap_or_default()
+                    .split(',')
+                    .filter(|byte| !byte.is_empty())
+                    .map(|byte| byte.parse().unwrap())
+                    .collect();
+
+                String::from_utf8(bytes).unwrap()
+            })
+            .collect()
+    }
+
+    fn assert_size(lines: &[String], width: usize, height: usize) {
+        assert_eq!(lines.len(), height);
+        for line in lines {
+            assert_eq!(line.chars().count(), width, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn two_pane_split() {
+        let editor = pane("editor", 0, 40, 0, 20);
+        let term = pane("term", 40, 40, 0, 20);
+
+        let lines = lines(&mini_map(&[&editor, &term], 9, 4));
+
+        assert_size(&lines, 9, 4);
+        assert!(lines[0].starts_with('┌') && lines[0].ends_with('┐'));
+        assert_eq!(lines[1], "│edi│ter│");
+        assert_eq!(lines[2], "│   │   │");
+        assert!(lines[3].starts_with('└') && lines[3].ends_with('┘'));
+    }
+
+    #[test]
+    fn panes_narrower_than_a_column_are_skipped() {
+        let left = pane("left", 0, 40, 0, 20);
+        let narrow = pane("narrow", 40, 1, 0, 20);
+        let right = pane("right", 41, 39, 0, 20);
+
+        let lines = lines(&mini_map(&[&left, &narrow, &right], 5, 4));
+
+        assert_size(&lines, 5, 4);
+        assert_eq!(lines[1], "│l│r│");
+    }
+
+    #[test]
+    fn small_maps_are_empty() {
+        let editor = pane("editor", 0, 80, 0, 20);
+
+        assert!(mini_map(&[&editor], 2, 10).is_empty());
+        assert!(mini_map(&[&editor], 10, 2).is_empty());
+        assert!(mini_map(&[&editor], 0, 0).is_empty());
+        assert_size(&lines(&mini_map(&[&editor], 3, 3)), 3, 3);
+        assert!(mini_map(&[], 10, 10).is_empty());
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+
+use zellij_tile::shim::{run_command, run_command_with_env_variables_and_cwd};
+
+use crate::command;
+
+/// Directory name for a new project from the search query. Whitespace and
+/// slashes are replaced, so that the query can be typed naturally and the
+/// directory is always created directly in the `scratch_path`. `.` and `..`
+/// give no name.
+pub fn directory_name(query: &str) -> String {
+    let name = query
+        .split(|c: char| c.is_whitespace() || c == '/')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-");
+
+    match name.as_str() {
+        "." | ".." => "".to_owned(),
+        _ => name,
+    }
+}
+
+/// Create the directory in the background.
+pub fn create(path: &str, name: &str) {
+    let context = command::context("mkdir", &[("path", path), ("name", name)]);
+
+    run_command(&["mkdir", "-p", path], context);
+}
+
+/// Run the configured init hook with `sh -c` in the new directory.
+pub fn init(hook: &str, path: &str, name: &str) {
+    let context = command::context("scratch_init", &[("path", path), ("name", name)]);
+
+    run_command_with_env_variables_and_cwd(
+        &["sh", "-c", hook],
+        BTreeMap::new(),
+        path.into(),
+        context,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directory_names() {
+        assert_eq!(directory_name("my new project"), "my-new-project");
+        assert_eq!(directory_name("  spaced\tout  "), "spaced-out");
+        assert_eq!(directory_name("/project/"), "project");
+        assert_eq!(directory_name("a/b"), "a-b");
+        assert_eq!(directory_name(".config"), ".config");
+    }
+
+    #[test]
+    fn directory_names_stay_in_scratch_path() {
+        assert_eq!(directory_name("../../tmp/x"), "..-..-tmp-x");
+        assert_eq!(directory_name(".."), "");
+        assert_eq!(directory_name("../"), "");
+        assert_eq!(directory_name(" . "), "");
+        assert_eq!(directory_name("/"), "");
+    }
+}
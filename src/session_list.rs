@@ -1,399 +1,1041 @@
 use zellij_tile::prelude::*;
 
-use rust_fuzzy_search::fuzzy_search_sorted;
+use rust_fuzzy_search::fuzzy_compare;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    format::{self, RowFormat, RowText},
+    hints,
+    history::{self, History},
+    metadata::Metadata,
+    pins::Pins,
+    preview,
+    sort::{SortKey, SortOrder},
+    tags::{self, Tags},
+};
+
+/// Maximum time between two clicks on the same row to attach to it.
+pub const DOUBLE_CLICK: Duration = Duration::from_millis(500);
+
+/// Columns per level of indentation, that toggle a node when clicked. They
+/// cover the indentation and the arrow of the node.
+const ARROW_COLUMNS: usize = 4;
+
+/// Top level rows of the list. Sessions are grouped under the header of
+/// their first tag, when any session is tagged.
+#[derive(Clone, Debug, PartialEq)]
+enum Row {
+    Header(String),
+    Session(usize),
+}
+
+/// Path of a node in the tree of sessions, tabs and panes. Nodes are
+/// identified by session name, tab position and pane id, so that the
+/// selection survives changes of the list.
+#[derive(Clone, Debug, PartialEq)]
+enum Cursor {
+    Header(String),
+    Session(String),
+    Tab(String, usize),
+    Pane(String, usize, u32),
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Self::Session("".to_owned())
+    }
+}
+
+impl Cursor {
+    fn session(&self) -> Option<&String> {
+        match self {
+            Self::Header(_) => None,
+            Self::Session(session) | Self::Tab(session, _) | Self::Pane(session, _, _) => {
+                Some(session)
+            }
+        }
+    }
+
+    fn tab(&self) -> Option<usize> {
+        match self {
+            Self::Tab(_, tab) | Self::Pane(_, tab, _) => Some(*tab),
+            _ => None,
+        }
+    }
+
+    fn pane(&self) -> Option<u32> {
+        match self {
+            Self::Pane(_, _, pane) => Some(*pane),
+            _ => None,
+        }
+    }
+}
+
+/// Items matching the query, best match first. Without a query, all items
+/// are kept in their order.
+fn fuzzy_filter<T: Clone>(query: &str, items: &[T], name: impl Fn(&T) -> &str) -> Vec<T> {
+    if query.is_empty() {
+        return items.to_vec();
+    }
+
+    let mut matches = items
+        .iter()
+        .map(|item| (fuzzy_compare(query, name(item)), item))
+        .filter(|(score, _)| *score > 0.0)
+        .collect::<Vec<(f32, &T)>>();
+    matches.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    matches.into_iter().map(|(_, item)| item.clone()).collect()
+}
+
+/// Move `offset` steps from the item at `position` and wrap around.
+fn wrap(position: usize, offset: i32, len: usize) -> usize {
+    (position as i32 + offset).rem_euclid(len as i32) as usize
+}
 
 #[derive(Default)]
 pub struct SessionList {
     sessions: Vec<SessionInfo>,
     filtered_sessions: Vec<SessionInfo>,
-    filtered_tabs: Vec<TabInfo>,
-    filtered_panes: Vec<PaneInfo>,
-    selected_session_index: usize,
-    selected_tab_index: usize,
-    selected_pane_index: usize,
     search_query: String,
-    session_is_expanded: bool,
-    tab_is_expanded: bool,
+    tab_query: String,
+    pane_query: String,
+    cursor: Cursor,
+    expanded_sessions: BTreeSet<String>,
+    expanded_tabs: BTreeSet<(String, usize)>,
+    tags: Tags,
+    collapsed_tags: BTreeSet<String>,
+    pins: Pins,
+    format: RowFormat,
+    metadata: Metadata,
+    sort: SortOrder,
+    /// First visible node and number of rows of the last rendered list.
+    offset: usize,
+    height: usize,
+    last_click: Option<(Cursor, Instant)>,
+}
+
+/// Tag, that a session is grouped under. Pinned sessions are not grouped,
+/// since they are always shown at the top.
+fn group_of<'a>(tags: &'a Tags, pins: &Pins, session: &str) -> Option<&'a String> {
+    match pins.contains(session) {
+        true => None,
+        false => tags.group(session),
+    }
 }
 
 impl SessionList {
-    pub fn new() -> Self {
+    pub fn new(config: &BTreeMap<String, String>) -> Self {
         Self {
-            sessions: vec![],
-            filtered_sessions: vec![],
-            filtered_tabs: vec![],
-            filtered_panes: vec![],
-            selected_session_index: 0,
-            selected_tab_index: 0,
-            selected_pane_index: 0,
-            search_query: "".to_owned(),
-            session_is_expanded: false,
-            tab_is_expanded: false,
+            tags: Tags::load(),
+            pins: Pins::sessions(),
+            format: RowFormat::from_config(config),
+            sort: SortOrder::from_config(config),
+            ..Default::default()
         }
     }
 
-    pub fn attach_selected(&mut self) {
-        let session = self
-            .filtered_sessions
-            .get(self.selected_session_index)
-            .unwrap();
+    fn group(&self, session: &str) -> Option<&String> {
+        group_of(&self.tags, &self.pins, session)
+    }
 
-        let tab = self.filtered_tabs.get(self.selected_tab_index).unwrap();
-        let pane = self.filtered_panes.get(self.selected_pane_index).unwrap();
+    pub fn sessions(&self) -> &[SessionInfo] {
+        &self.sessions
+    }
 
-        tracing::debug!(
-            "session {} tab {} pane {}",
-            session.name,
-            tab.name,
-            pane.title
-        );
+    fn session(&self, name: &str) -> Option<&SessionInfo> {
+        self.filtered_sessions.iter().find(|s| s.name == name)
+    }
 
-        if session.is_current_session {
-            switch_tab_to(tab.position as u32 + 1u32);
+    /// Tabs of the session, that match the tab query.
+    fn tabs(&self, session: &SessionInfo) -> Vec<TabInfo> {
+        fuzzy_filter(&self.tab_query, &session.tabs, |tab| &tab.name)
+    }
 
-            return;
+    /// Selectable panes of the tab, that match the pane query.
+    fn panes(&self, session: &SessionInfo, tab: usize) -> Vec<PaneInfo> {
+        let panes = session
+            .panes
+            .panes
+            .get(&tab)
+            .map(|panes| {
+                panes
+                    .iter()
+                    .filter(|pane| pane.is_selectable)
+                    .cloned()
+                    .collect::<Vec<PaneInfo>>()
+            })
+            .unwrap_or_default();
+
+        fuzzy_filter(&self.pane_query, &panes, |pane| &pane.title)
+    }
+
+    /// Details of the selected session or, when a tab is selected, a
+    /// mini-map of its panes.
+    pub fn preview(&self, width: usize, height: usize) -> Vec<Text> {
+        let session = match self.cursor.session().and_then(|name| self.session(name)) {
+            Some(session) => session,
+            None => return vec![],
+        };
+
+        let tab = self
+            .cursor
+            .tab()
+            .and_then(|position| session.tabs.iter().find(|tab| tab.position == position));
+
+        match tab {
+            Some(tab) => preview::tab(session, tab, width, height),
+            None => preview::session(session, self.metadata.get(&session.name)),
         }
+    }
 
-        switch_session_with_focus(&session.name, Some(tab.position), Some((pane.id, false)));
+    pub fn toggle_pin_selected(&mut self) {
+        let name = match self.selected_session_name() {
+            Some(name) => name,
+            None => return,
+        };
+
+        self.pins.toggle(&name);
+        self.refilter();
     }
 
-    pub fn delete_selected(&mut self) {
-        let session = self
-            .filtered_sessions
-            .get(self.selected_session_index)
-            .unwrap();
+    fn is_grouped(&self) -> bool {
+        !self.tags.is_empty()
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = vec![];
+        let mut last_group: Option<&String> = None;
+
+        for (index, session) in self.filtered_sessions.iter().enumerate() {
+            let group = self.group(&session.name);
+
+            if self.is_grouped() && group != last_group {
+                if let Some(tag) = group {
+                    rows.push(Row::Header(tag.to_owned()));
+                }
+                last_group = group;
+            }
+
+            if group.is_some_and(|tag| self.collapsed_tags.contains(tag)) {
+                continue;
+            }
+
+            rows.push(Row::Session(index));
+        }
 
-        kill_sessions(&[&session.name]);
+        rows
     }
 
-    pub fn expand(&mut self) {
-        if !self.session_is_expanded {
-            self.session_is_expanded = true;
-            self.selected_tab_index = 0;
-        } else {
-            self.tab_is_expanded = true;
-            self.selected_pane_index = 0;
+    /// Nodes of the tree, that are shown, in the order of the list. Tabs
+    /// and panes are shown below expanded sessions and tabs.
+    fn visible(&self) -> Vec<Cursor> {
+        let mut nodes = vec![];
+
+        for row in self.rows() {
+            let session = match row {
+                Row::Header(tag) => {
+                    nodes.push(Cursor::Header(tag));
+                    continue;
+                }
+                Row::Session(index) => &self.filtered_sessions[index],
+            };
+            let name = &session.name;
+
+            nodes.push(Cursor::Session(name.to_owned()));
+
+            if !self.expanded_sessions.contains(name) {
+                continue;
+            }
+
+            for tab in self.tabs(session) {
+                nodes.push(Cursor::Tab(name.to_owned(), tab.position));
+
+                if !self
+                    .expanded_tabs
+                    .contains(&(name.to_owned(), tab.position))
+                {
+                    continue;
+                }
+
+                for pane in self.panes(session, tab.position) {
+                    nodes.push(Cursor::Pane(name.to_owned(), tab.position, pane.id));
+                }
+            }
         }
+
+        nodes
     }
 
-    pub fn shrink(&mut self) {
-        if self.tab_is_expanded {
-            self.tab_is_expanded = false;
+    fn is_expanded(&self, node: &Cursor) -> bool {
+        match node {
+            Cursor::Header(tag) => !self.collapsed_tags.contains(tag),
+            Cursor::Session(name) => self.expanded_sessions.contains(name),
+            Cursor::Tab(name, tab) => self.expanded_tabs.contains(&(name.to_owned(), *tab)),
+            Cursor::Pane(..) => false,
+        }
+    }
 
-            return;
+    /// Position of the cursor in the visible nodes.
+    fn position(&self) -> usize {
+        self.visible()
+            .iter()
+            .position(|node| *node == self.cursor)
+            .unwrap_or_default()
+    }
+
+    /// Expand or collapse the selected tag header. Returns false, when a
+    /// session is selected instead.
+    pub fn toggle_selected_group(&mut self) -> bool {
+        let tag = match &self.cursor {
+            Cursor::Header(tag) => tag.to_owned(),
+            _ => return false,
+        };
+
+        if !self.collapsed_tags.remove(&tag) {
+            self.collapsed_tags.insert(tag);
         }
 
-        self.session_is_expanded = false;
+        true
     }
 
-    pub fn update_sessions(&mut self, sessions: Vec<SessionInfo>) {
-        self.sessions = sessions;
+    pub fn selected_session_name(&self) -> Option<String> {
+        self.cursor
+            .session()
+            .filter(|name| self.session(name).is_some())
+            .cloned()
+    }
+
+    /// Tags of the selected session, separated by spaces.
+    pub fn selected_tags(&self) -> String {
+        self.selected_session_name()
+            .and_then(|name| self.tags.get(&name).cloned())
+            .unwrap_or_default()
+            .into_iter()
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    pub fn set_selected_tags(&mut self, input: &str) {
+        let name = match self.selected_session_name() {
+            Some(name) => name,
+            None => return,
+        };
+
+        self.tags.set(&name, tags::parse(input));
+        self.tags.save();
+
+        self.refilter();
+    }
+
+    pub fn sort_order(&self) -> SortOrder {
+        self.sort
+    }
+
+    /// Configuration values in effect for the session list.
+    pub fn settings(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("session_format", self.format.template().to_owned()),
+            ("sort", self.sort.label().to_owned()),
+        ]
+    }
+
+    /// Switch to the next sort order. The selected session stays selected.
+    pub fn cycle_sort(&mut self) {
+        self.sort = self.sort.next();
+        self.refilter();
+    }
+
+    /// Filter again with the same query, e.g. after the tags, pins or the
+    /// sort order changed. The selection is kept.
+    fn refilter(&mut self) {
         self.filter(&self.search_query.clone());
     }
 
-    fn filter_tabs_for_selected_session(&mut self, search_query: &str) {
-        tracing::debug!("selected_session_index {}", self.selected_session_index);
+    /// Apply `#tag` filters and order the sessions with pinned ones first,
+    /// followed by the tag groups.
+    fn filter_tags(&mut self, filters: &[String]) {
+        let tags = &self.tags;
+        let pins = &self.pins;
+        let metadata = &self.metadata;
+
+        self.filtered_sessions
+            .retain(|session| tags.matches(&session.name, filters));
+
+        let history = match self.sort {
+            SortOrder::Frecency => History::load(),
+            _ => History::default(),
+        };
+        self.sort.sort(&mut self.filtered_sessions, |session| {
+            SortKey::session(
+                &session.name,
+                Some(session),
+                metadata.get(&session.name),
+                history.recency(&session.name),
+            )
+        });
+
+        self.filtered_sessions.sort_by_key(|session| {
+            let group = group_of(tags, pins, &session.name);
+
+            (
+                !pins.contains(&session.name),
+                group.is_none(),
+                group.cloned(),
+            )
+        });
+    }
 
-        let session = match self.filtered_sessions.get(self.selected_session_index) {
-            Some(s) => s,
+    pub fn attach_selected(&mut self) {
+        let session = match self.cursor.session().and_then(|name| self.session(name)) {
+            Some(session) => session,
             None => return,
         };
+        let tab = self.cursor.tab();
+        let pane = self.cursor.pane();
 
-        if search_query.is_empty() {
-            self.filtered_tabs = session.tabs.clone();
-            self.filter_panes_for_selected_tab(search_query);
+        tracing::debug!("session {} tab {:?} pane {:?}", session.name, tab, pane);
 
-            if self.tab_is_expanded && !self.search_query.is_empty() {
-                self.tab_is_expanded = false;
+        if session.is_current_session {
+            if let Some(tab) = tab {
+                switch_tab_to(tab as u32 + 1u32);
             }
 
-            self.search_query = search_query.to_owned();
-
             return;
         }
 
-        let mut search_query = search_query;
-        let mut pane_query = "".to_owned();
-        if search_query.contains(' ') {
-            let mut parts = search_query.split(' ').collect::<Vec<&str>>();
+        history::visit(&session.name);
+        switch_session_with_focus(&session.name, tab, pane.map(|pane| (pane, false)));
+    }
 
-            search_query = parts[0];
-            parts.drain(..1);
-            pane_query = parts.join(" ");
-            tracing::debug!("pane_query: |{}|", pane_query);
+    /// Sessions in the rendered window of the list, in the order of their
+    /// hints.
+    fn hinted_sessions(&self) -> Vec<String> {
+        self.visible()
+            .into_iter()
+            .skip(self.offset)
+            .take(self.height)
+            .filter_map(|node| match node {
+                Cursor::Session(name) => Some(name),
+                _ => None,
+            })
+            .collect()
+    }
 
-            self.tab_is_expanded = true;
-        } else {
-            self.tab_is_expanded = false;
+    /// Attach to the session with the hint at `index`, without changing the
+    /// selection. Returns false, when there is no such session.
+    pub fn attach_index(&mut self, index: usize) -> bool {
+        let session = match self
+            .hinted_sessions()
+            .get(index)
+            .and_then(|name| self.session(name))
+        {
+            Some(session) => session,
+            None => return false,
+        };
+
+        if !session.is_current_session {
+            history::visit(&session.name);
+            switch_session_with_focus(&session.name, None, None);
         }
 
-        let tab_names = session
-            .tabs
-            .iter()
-            .map(|t| t.name.as_str())
-            .collect::<Vec<&str>>();
+        true
+    }
 
-        let result = fuzzy_search_sorted(search_query, &tab_names)
-            .iter()
-            .filter(|(_, score)| *score > 0.0)
-            .map(|(c, _)| c.to_string())
-            .collect::<Vec<String>>();
+    pub fn delete_selected(&mut self) {
+        if let Some(name) = self.selected_session_name() {
+            kill_sessions(&[&name]);
+        }
+    }
 
-        self.filtered_tabs = result
-            .into_iter()
-            .map(|tn| session.tabs.iter().find(|t| t.name == tn).unwrap().clone())
-            .collect();
+    /// First tab of the selected session or first pane of the selected tab.
+    fn first_child(&self) -> Option<Cursor> {
+        let session = self.cursor.session().and_then(|name| self.session(name))?;
+
+        match &self.cursor {
+            Cursor::Session(name) => self
+                .tabs(session)
+                .first()
+                .map(|tab| Cursor::Tab(name.to_owned(), tab.position)),
+            Cursor::Tab(name, tab) => self
+                .panes(session, *tab)
+                .first()
+                .map(|pane| Cursor::Pane(name.to_owned(), *tab, pane.id)),
+            _ => None,
+        }
+    }
 
-        self.selected_tab_index = 0;
+    /// Expand the selected node and select its first child.
+    fn open(&mut self) {
+        match &self.cursor {
+            Cursor::Session(name) => {
+                self.expanded_sessions.insert(name.to_owned());
+            }
+            Cursor::Tab(name, tab) => {
+                self.expanded_tabs.insert((name.to_owned(), *tab));
+            }
+            _ => return,
+        }
 
-        self.filter_panes_for_selected_tab(&pane_query);
+        if let Some(child) = self.first_child() {
+            self.cursor = child;
+        }
     }
 
-    fn filter_panes_for_selected_tab(&mut self, search_query: &str) {
-        tracing::debug!("selected_pane_index {}", self.selected_session_index);
+    /// Expand the selected node or, when it is expanded already, select its
+    /// first child.
+    pub fn expand(&mut self) {
+        match &self.cursor {
+            Cursor::Header(tag) => {
+                self.collapsed_tags.remove(tag);
+            }
+            Cursor::Session(name) if !self.expanded_sessions.contains(name) => {
+                self.expanded_sessions.insert(name.to_owned());
+            }
+            Cursor::Tab(name, tab) if !self.expanded_tabs.contains(&(name.to_owned(), *tab)) => {
+                self.expanded_tabs.insert((name.to_owned(), *tab));
+            }
+            _ => self.open(),
+        }
+    }
 
-        let session = match self.filtered_sessions.get(self.selected_session_index) {
-            Some(s) => s,
-            None => return,
+    /// Collapse the selected node or, when it is collapsed already, select
+    /// its parent.
+    pub fn shrink(&mut self) {
+        if self.is_expanded(&self.cursor) {
+            match &self.cursor {
+                Cursor::Header(tag) => self.collapsed_tags.insert(tag.to_owned()),
+                Cursor::Session(name) => self.expanded_sessions.remove(name),
+                Cursor::Tab(name, tab) => self.expanded_tabs.remove(&(name.to_owned(), *tab)),
+                Cursor::Pane(..) => false,
+            };
+
+            return;
+        }
+
+        self.cursor = match &self.cursor {
+            Cursor::Header(_) => return,
+            Cursor::Pane(name, tab, _) => Cursor::Tab(name.to_owned(), *tab),
+            Cursor::Tab(name, _) => Cursor::Session(name.to_owned()),
+            Cursor::Session(name) => match self.group(name) {
+                Some(tag) => Cursor::Header(tag.to_owned()),
+                None => return,
+            },
         };
+    }
 
-        tracing::debug!("selected session {}", session.name);
+    /// Expand all groups, sessions and tabs.
+    pub fn expand_all(&mut self) {
+        self.collapsed_tags.clear();
 
-        let tab = self.filtered_tabs.get(self.selected_tab_index).unwrap();
-        tracing::debug!("selected tab: {}", tab.name);
-        let panes = session
-            .panes
-            .panes
-            .get(&tab.position)
-            .unwrap()
+        for session in &self.filtered_sessions {
+            self.expanded_sessions.insert(session.name.to_owned());
+
+            for tab in self.tabs(session) {
+                self.expanded_tabs
+                    .insert((session.name.to_owned(), tab.position));
+            }
+        }
+    }
+
+    /// Collapse all sessions and tabs and select the session, that the
+    /// cursor was in.
+    pub fn collapse_all(&mut self) {
+        self.expanded_sessions.clear();
+        self.expanded_tabs.clear();
+
+        if let Some(name) = self.cursor.session() {
+            self.cursor = Cursor::Session(name.to_owned());
+        }
+    }
+
+    pub fn update_sessions(&mut self, sessions: Vec<SessionInfo>, metadata: &Metadata) {
+        self.sessions = sessions;
+        self.metadata = metadata.clone();
+        self.refilter();
+    }
+
+    /// Filter the sessions with a query of the form `session tab pane`, where
+    /// `#tag` filters can be added anywhere. A new query selects the best
+    /// match; with the same query, e.g. after a `SessionUpdate`, the selected
+    /// node stays selected.
+    pub fn filter(&mut self, full_query: &str) {
+        let is_new_query = self.search_query != full_query;
+        let previous = self.position();
+
+        self.search_query = full_query.to_owned();
+
+        let (search_query, tag_filters) = tags::split_query(full_query);
+        let mut parts = search_query.splitn(3, ' ');
+        let session_query = parts.next().unwrap_or_default().to_owned();
+        let tab_query = parts.next();
+        let pane_query = parts.next();
+
+        self.tab_query = tab_query.unwrap_or_default().to_owned();
+        self.pane_query = pane_query.unwrap_or_default().to_owned();
+
+        self.filtered_sessions = fuzzy_filter(&session_query, &self.sessions, |s| &s.name);
+        self.filter_tags(&tag_filters);
+
+        if is_new_query && !search_query.is_empty() {
+            self.select_best_match(tab_query.is_some(), pane_query.is_some());
+        } else {
+            self.repair_cursor(previous);
+        }
+    }
+
+    /// Select the first session and, for tab or pane queries, expand it
+    /// and select its first tab and pane.
+    fn select_best_match(&mut self, select_tab: bool, select_pane: bool) {
+        let nodes = self.visible();
+
+        self.cursor = nodes
             .iter()
-            .filter(|p| p.is_selectable)
+            .find(|node| matches!(node, Cursor::Session(_)))
+            .or_else(|| nodes.first())
             .cloned()
-            .collect::<Vec<PaneInfo>>();
+            .unwrap_or_default();
 
-        tracing::debug!("panes: {:?}", panes.len());
-        if search_query.is_empty() {
-            self.filtered_panes = panes.to_vec();
+        if select_tab {
+            self.open();
+        }
+        if select_pane {
+            self.open();
+        }
+    }
+
+    /// Keep the cursor on a visible node. When the selected node
+    /// disappeared, the node at the same position is selected instead.
+    fn repair_cursor(&mut self, previous: usize) {
+        let nodes = self.visible();
 
+        if !nodes.contains(&self.cursor) {
+            self.cursor = nodes
+                .get(previous.min(nodes.len().saturating_sub(1)))
+                .cloned()
+                .unwrap_or_default();
+        }
+    }
+
+    /// Move the cursor through the visible nodes.
+    fn select(&mut self, offset: i32) {
+        let nodes = self.visible();
+        if nodes.is_empty() {
             return;
         }
 
-        let pane_names = panes
+        let position = nodes
             .iter()
-            .map(|p| p.title.as_str())
-            .collect::<Vec<&str>>();
+            .position(|node| *node == self.cursor)
+            .unwrap_or_default();
 
-        let result = fuzzy_search_sorted(search_query, &pane_names)
-            .iter()
-            .filter(|(_, score)| *score > 0.0)
-            .map(|(c, _)| c.to_string())
-            .collect::<Vec<String>>();
+        self.cursor = nodes[wrap(position, offset, nodes.len())].clone();
+    }
 
-        self.filtered_panes = result
-            .into_iter()
-            .map(|pn| panes.iter().find(|p| p.title == pn).unwrap().clone())
-            .collect();
+    pub fn select_next(&mut self) {
+        self.select(1);
+    }
 
-        self.selected_pane_index = 0;
+    pub fn select_prev(&mut self) {
+        self.select(-1);
     }
 
-    pub fn filter(&mut self, search_query: &str) {
-        if self.sessions.is_empty() {
-            return;
+    /// Arrow in front of a node, that shows whether it is expanded.
+    fn arrow(&self, node: &Cursor) -> &'static str {
+        match self.is_expanded(node) {
+            true => "▾ ",
+            false => "▸ ",
+        }
+    }
+
+    /// Scroll the viewport just enough to show the cursor.
+    fn scroll(&mut self, len: usize, height: usize) {
+        let position = self.position();
+
+        if position < self.offset {
+            self.offset = position;
+        } else if position >= self.offset + height {
+            self.offset = position + 1 - height;
+        }
+
+        self.offset = self.offset.min(len.saturating_sub(height));
+    }
+
+    /// Select the node in the row of the rendered list. Clicks on the arrow
+    /// expand or collapse the node. Returns true for a double click, that
+    /// should attach to the node.
+    pub fn click(&mut self, row: usize, column: usize) -> bool {
+        if row >= self.height {
+            return false;
         }
 
-        if search_query.is_empty() {
-            self.filtered_sessions = self.sessions.clone();
-            self.filter_tabs_for_selected_session(search_query);
+        let node = match self.visible().get(self.offset + row) {
+            Some(node) => node.clone(),
+            None => return false,
+        };
+
+        let is_double_click = self
+            .last_click
+            .as_ref()
+            .is_some_and(|(last, time)| *last == node && time.elapsed() < DOUBLE_CLICK);
+        self.last_click = Some((node.clone(), Instant::now()));
+        self.cursor = node.clone();
+
+        let indent = match &node {
+            Cursor::Header(_) => 0,
+            Cursor::Session(name) => self.indent(name),
+            Cursor::Tab(name, _) => self.indent(name) + 1,
+            Cursor::Pane(..) => return is_double_click,
+        };
+
+        if column < (indent + 1) * ARROW_COLUMNS {
+            self.last_click = None;
 
-            if self.session_is_expanded && !self.search_query.is_empty() {
-                self.session_is_expanded = false;
+            match self.is_expanded(&node) {
+                true => self.shrink(),
+                false => self.expand(),
             }
 
-            self.search_query = search_query.to_owned();
+            return false;
+        }
 
-            return;
+        if is_double_click {
+            self.last_click = None;
         }
 
-        self.search_query = search_query.to_owned();
+        is_double_click
+    }
+
+    pub fn get_list(&mut self, height: usize) -> Vec<NestedListItem> {
+        let mut output: Vec<NestedListItem> = vec![];
+        let nodes = self.visible();
 
-        let mut search_query = search_query;
-        let mut tab_query = "".to_owned();
-        if search_query.contains(' ') {
-            let mut parts = search_query.split(' ').collect::<Vec<&str>>();
+        self.height = height;
+        self.scroll(nodes.len(), height);
 
-            search_query = parts[0];
-            parts.drain(..1);
-            tab_query = parts.join(" ");
-            tracing::debug!("tab_query: |{}|", tab_query);
+        let mut hint = 0;
 
-            self.session_is_expanded = true;
-        } else {
-            self.session_is_expanded = false;
+        for node in nodes.into_iter().skip(self.offset).take(height) {
+            let mut item = match &node {
+                Cursor::Header(tag) => self.header_item(tag),
+                Cursor::Session(name) => {
+                    hint += 1;
+                    self.session_item(&node, name, hint - 1)
+                }
+                Cursor::Tab(name, tab) => self.tab_item(&node, name, *tab),
+                Cursor::Pane(name, tab, pane) => self.pane_item(name, *tab, *pane),
+            };
+
+            if node == self.cursor {
+                item = item.selected();
+            }
+
+            output.push(item);
         }
 
-        let session_names = self
-            .sessions
-            .iter()
-            .map(|s| s.name.as_str())
-            .collect::<Vec<&str>>();
+        output
+    }
 
-        let result = fuzzy_search_sorted(search_query, &session_names)
+    fn header_item(&self, tag: &String) -> NestedListItem {
+        let count = self
+            .filtered_sessions
             .iter()
-            .filter(|(_, score)| *score > 0.0)
-            .map(|(c, _)| c.to_string())
-            .collect::<Vec<String>>();
+            .filter(|s| self.group(&s.name) == Some(tag))
+            .count();
 
-        tracing::debug!("fuzzy_search result: {:?}", result);
+        let mut row = RowText::new();
+        row.push(self.arrow(&Cursor::Header(tag.to_owned())), None);
+        row.push(&format!("#{}", tag), Some(2));
+        row.push(&format!(" ({} sessions)", count), None);
 
-        self.filtered_sessions = result
-            .into_iter()
-            .map(|sn| self.sessions.iter().find(|s| s.name == sn).unwrap().clone())
-            .collect();
+        row.item()
+    }
 
-        self.selected_session_index = 0;
+    /// Indentation of the rows of a session, that is grouped under a tag.
+    fn indent(&self, name: &str) -> usize {
+        match self.group(name) {
+            Some(_) => 1,
+            None => 0,
+        }
+    }
 
-        self.filter_tabs_for_selected_session(&tab_query);
+    fn session_item(&self, node: &Cursor, name: &str, hint: usize) -> NestedListItem {
+        let session = match self.session(name) {
+            Some(session) => session,
+            None => return NestedListItem::new(name),
+        };
+
+        let mut row = RowText::new();
+        row.push(self.arrow(node), None);
+        row.push(&hints::prefix(hint), Some(3));
+        self.format.render(
+            &mut row,
+            &format::session_values(&session.name, session, self.metadata.get(&session.name)),
+        );
+
+        // Show the tags, that the session is not grouped under.
+        let other_tags = self
+            .tags
+            .get(&session.name)
+            .map(|tags| {
+                tags.iter().skip(1).fold(String::new(), |mut other, tag| {
+                    let _ = write!(other, " #{}", tag);
+                    other
+                })
+            })
+            .unwrap_or_default();
+        row.push(&other_tags, Some(3));
+
+        if self.pins.contains(&session.name) {
+            row.push(" ", None);
+            row.push("★", Some(2));
+        }
+
+        row.item().indent(self.indent(name))
     }
 
-    pub fn select_next(&mut self) {
-        if self.sessions.is_empty() {
-            return;
+    fn tab_item(&self, node: &Cursor, name: &str, position: usize) -> NestedListItem {
+        let session = self.session(name);
+        let tab = session.and_then(|s| s.tabs.iter().find(|tab| tab.position == position));
+        let (session, tab) = match (session, tab) {
+            (Some(session), Some(tab)) => (session, tab),
+            _ => return NestedListItem::new(name),
+        };
+
+        let mut row = RowText::new();
+        row.push(self.arrow(node), None);
+        row.push(&tab.name, Some(1));
+        row.push(" (", None);
+        row.push(&self.panes(session, position).len().to_string(), Some(2));
+        row.push(" panes)", None);
+
+        row.item().indent(self.indent(name) + 1)
+    }
+
+    fn pane_item(&self, name: &str, tab: usize, id: u32) -> NestedListItem {
+        let title = self
+            .session(name)
+            .map(|session| self.panes(session, tab))
+            .unwrap_or_default()
+            .into_iter()
+            .find(|pane| pane.id == id)
+            .map(|pane| pane.title)
+            .unwrap_or_default();
+
+        NestedListItem::new(title).indent(self.indent(name) + 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: &[&str] = &["alpha", "beta", "api", "backend", "logs"];
+    const QUERIES: &[&str] = &[
+        "",
+        "a",
+        "al",
+        "zz",
+        "a t",
+        "a tab p",
+        "b  ",
+        "#work",
+        "api #rust",
+        "#none",
+    ];
+
+    /// Small xorshift generator, so that failures can be reproduced from
+    /// the seed.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
         }
 
-        if self.session_is_expanded {
-            if self.tab_is_expanded {
-                let tab = self.filtered_tabs.get(self.selected_tab_index).unwrap();
-                let pane_count = self
-                    .filtered_sessions
-                    .get(self.selected_session_index)
-                    .unwrap()
-                    .panes
-                    .panes
-                    .get(&tab.position)
-                    .unwrap()
-                    .iter()
-                    .filter(|t| t.is_selectable)
-                    .count();
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
 
-                self.selected_pane_index =
-                    (self.selected_pane_index as i32 + 1).rem_euclid(pane_count as i32) as usize;
+    fn random_sessions(rng: &mut Rng) -> Vec<SessionInfo> {
+        let mut sessions = vec![];
 
-                return;
+        for name in NAMES {
+            if rng.below(4) == 0 {
+                continue;
             }
 
-            self.selected_tab_index = (self.selected_tab_index as i32 + 1)
-                .rem_euclid(self.filtered_tabs.len() as i32)
-                as usize;
+            let mut session = SessionInfo {
+                name: name.to_string(),
+                ..Default::default()
+            };
+
+            for position in 0..rng.below(4) {
+                session.tabs.push(TabInfo {
+                    position,
+                    name: format!("tab {}", position),
+                    ..Default::default()
+                });
+
+                let panes = (0..rng.below(4))
+                    .map(|id| PaneInfo {
+                        id: id as u32,
+                        title: format!("pane {}", id),
+                        is_selectable: rng.below(4) > 0,
+                        ..Default::default()
+                    })
+                    .collect();
+                session.panes.panes.insert(position, panes);
+            }
 
-            return;
+            sessions.push(session);
         }
 
-        self.selected_session_index = (self.selected_session_index as i32 + 1)
-            .rem_euclid(self.filtered_sessions.len() as i32)
-            as usize;
+        sessions
     }
 
-    pub fn select_prev(&mut self) {
-        if self.sessions.is_empty() {
-            return;
+    /// Session with two tabs, that both contain the panes.
+    fn session(name: &str, panes: &[u32]) -> SessionInfo {
+        let mut session = SessionInfo {
+            name: name.to_owned(),
+            ..Default::default()
+        };
+
+        for position in 0..2 {
+            session.tabs.push(TabInfo {
+                position,
+                name: format!("tab {}", position),
+                ..Default::default()
+            });
+
+            let panes = panes
+                .iter()
+                .map(|&id| PaneInfo {
+                    id,
+                    title: format!("pane {}", id),
+                    is_selectable: true,
+                    ..Default::default()
+                })
+                .collect();
+            session.panes.panes.insert(position, panes);
         }
 
-        if self.session_is_expanded {
-            if self.tab_is_expanded {
-                let tab = self.filtered_tabs.get(self.selected_tab_index).unwrap();
-                let pane_count = self
-                    .filtered_sessions
-                    .get(self.selected_session_index)
-                    .unwrap()
-                    .panes
-                    .panes
-                    .get(&tab.position)
-                    .unwrap()
-                    .iter()
-                    .filter(|t| t.is_selectable)
-                    .count();
+        session
+    }
 
-                self.selected_pane_index =
-                    (self.selected_pane_index as i32 - 1).rem_euclid(pane_count as i32) as usize;
+    fn update(list: &mut SessionList, names: &[&str]) {
+        let sessions = names.iter().map(|name| session(name, &[1, 7])).collect();
+        list.update_sessions(sessions, &Metadata::default());
+    }
 
-                return;
-            }
-            self.selected_tab_index = (self.selected_tab_index as i32 - 1)
-                .rem_euclid(self.filtered_tabs.len() as i32)
-                as usize;
+    /// List with the cursor on pane 7 in the second tab of `beta`.
+    fn list_on_pane() -> SessionList {
+        let mut list = SessionList::default();
+        update(&mut list, &["alpha", "beta", "gamma"]);
+
+        list.select_next();
+        list.expand();
+        list.expand();
+        list.select_next();
+        list.expand();
+        list.expand();
+        list.select_next();
+        assert_eq!(list.cursor, Cursor::Pane("beta".to_owned(), 1, 7));
+
+        list
+    }
 
-            return;
-        }
+    #[test]
+    fn selection_stays_on_the_pane_across_reordering_updates() {
+        let mut list = list_on_pane();
 
-        self.selected_session_index = (self.selected_session_index as i32 - 1)
-            .rem_euclid(self.filtered_sessions.len() as i32)
-            as usize;
+        update(&mut list, &["delta", "gamma", "beta", "alpha"]);
+
+        assert_eq!(list.cursor, Cursor::Pane("beta".to_owned(), 1, 7));
+        assert_eq!(list.position(), 6);
     }
 
-    pub fn get_list(&self) -> Vec<NestedListItem> {
-        let mut output: Vec<NestedListItem> = vec![];
+    #[test]
+    fn selection_falls_back_to_the_same_index_when_the_session_is_removed() {
+        let mut list = list_on_pane();
+        let position = list.position();
+        assert_eq!(position, 5);
 
-        for (index, session) in self.filtered_sessions.clone().into_iter().enumerate() {
-            let mut item = NestedListItem::new(format!(
-                "{} ({} tabs, {} panes) [{} connected users]",
-                &session.name,
-                session.tabs.len(),
-                session.panes.panes.len(),
-                session.connected_clients,
-            ))
-            .color_range(0, 0..session.name.len())
-            .color_range(1, session.name.len() + 2..session.name.len() + 3)
-            .color_range(2, session.name.len() + 10..session.name.len() + 11)
-            .color_range(0, session.name.len() + 20..session.name.len() + 21);
-
-            if index == self.selected_session_index && !self.session_is_expanded {
-                item = item.selected();
-            }
+        update(
+            &mut list,
+            &["alpha", "gamma", "delta", "epsilon", "zeta", "eta"],
+        );
 
-            output.push(item);
+        assert_eq!(list.cursor, Cursor::Session("eta".to_owned()));
+        assert_eq!(list.position(), position);
 
-            if index == self.selected_session_index && self.session_is_expanded {
-                for (tab_index, tab) in self.filtered_tabs.clone().into_iter().enumerate() {
-                    let mut tab_item = NestedListItem::new(format!(
-                        "{} ({} panes)",
-                        &tab.name,
-                        session
-                            .panes
-                            .panes
-                            .get(&tab.position)
-                            .unwrap()
-                            .iter()
-                            .filter(|t| t.is_selectable)
-                            .count(),
-                    ))
-                    .color_range(1, 0..tab.name.len())
-                    .color_range(2, tab.name.len() + 2..tab.name.len() + 3);
-
-                    tab_item = tab_item.indent(1);
-
-                    if tab_index == self.selected_tab_index && !self.tab_is_expanded {
-                        tab_item = tab_item.selected();
-                    }
+        update(&mut list, &["alpha"]);
 
-                    output.push(tab_item);
+        assert_eq!(list.cursor, Cursor::Session("alpha".to_owned()));
+    }
 
-                    tracing::debug!("tab_is_expanded {}", self.tab_is_expanded);
-                    if tab_index == self.selected_tab_index && self.tab_is_expanded {
-                        for (pane_id, pane) in self.filtered_panes.clone().iter().enumerate() {
-                            let mut pane_item = NestedListItem::new(&pane.title).indent(2);
+    fn tagged_list() -> SessionList {
+        let mut list = SessionList::default();
+        for &(session, tag) in &[("alpha", "work"), ("api", "work"), ("logs", "rust")] {
+            list.tags.set(session, BTreeSet::from([tag.to_owned()]));
+        }
 
-                            if pane_id == self.selected_pane_index {
-                                pane_item = pane_item.selected();
-                            }
+        list
+    }
 
-                            output.push(pane_item);
-                        }
+    #[test]
+    fn cursor_stays_visible_for_random_operations() {
+        for seed in 1..=200 {
+            let mut rng = Rng(seed);
+            let mut list = match seed % 2 {
+                0 => SessionList::default(),
+                _ => tagged_list(),
+            };
+            let mut operations = vec![];
+
+            for _ in 0..200 {
+                let operation = rng.below(11);
+                operations.push(operation);
+
+                match operation {
+                    0 => list.update_sessions(random_sessions(&mut rng), &Metadata::default()),
+                    1 => list.filter(QUERIES[rng.below(QUERIES.len())]),
+                    2 => list.select_next(),
+                    3 => list.select_prev(),
+                    4 => list.expand(),
+                    5 => list.shrink(),
+                    6 => list.expand_all(),
+                    7 => list.collapse_all(),
+                    8 => {
+                        list.toggle_selected_group();
+                    }
+                    9 => {
+                        list.get_list(rng.below(8));
+                    }
+                    _ => {
+                        list.click(rng.below(10), rng.below(16));
                     }
                 }
+
+                let visible = list.visible();
+                assert!(
+                    visible.is_empty() || visible.contains(&list.cursor),
+                    "seed {}: {:?} after {:?} is not in {:?}",
+                    seed,
+                    list.cursor,
+                    operations,
+                    visible
+                );
             }
         }
-
-        output
     }
 }
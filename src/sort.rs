@@ -0,0 +1,120 @@
+use std::{cmp::Ordering, collections::BTreeMap};
+
+use zellij_tile::prelude::SessionInfo;
+
+use crate::{format, metadata::SessionMetadata};
+
+/// Order of the lists, configured with `sort` and cycled with `Ctrl r`.
+/// Pinned items and tag groups always stay on top.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SortOrder {
+    /// Fuzzy score of the search or, without a search, the natural order.
+    #[default]
+    Score,
+    Alphabetical,
+    /// Frecency of directories and most recently used sessions first.
+    Frecency,
+    /// Most tabs first, then most panes.
+    Size,
+    /// Most recently changed sessions first.
+    Activity,
+    /// Sessions with connected users first.
+    Attached,
+}
+
+const ORDERS: [SortOrder; 6] = [
+    SortOrder::Score,
+    SortOrder::Alphabetical,
+    SortOrder::Frecency,
+    SortOrder::Size,
+    SortOrder::Activity,
+    SortOrder::Attached,
+];
+
+impl SortOrder {
+    pub fn from_config(config: &BTreeMap<String, String>) -> Self {
+        config
+            .get("sort")
+            .and_then(|sort| ORDERS.iter().find(|order| order.label() == sort))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn next(self) -> Self {
+        let index = ORDERS
+            .iter()
+            .position(|order| *order == self)
+            .unwrap_or_default();
+
+        ORDERS[(index + 1) % ORDERS.len()]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Score => "score",
+            Self::Alphabetical => "alphabetical",
+            Self::Frecency => "frecency",
+            Self::Size => "size",
+            Self::Activity => "activity",
+            Self::Attached => "attached",
+        }
+    }
+
+    /// Sort the items by this order. Ties are resolved by name, so that the
+    /// order does not depend on the previous one. The score order keeps
+    /// the items as they are.
+    pub fn sort<T>(&self, items: &mut Vec<T>, key: impl Fn(&T) -> SortKey) {
+        if *self == Self::Score {
+            return;
+        }
+
+        let mut keyed = std::mem::take(items)
+            .into_iter()
+            .map(|item| (key(&item), item))
+            .collect::<Vec<(SortKey, T)>>();
+
+        keyed.sort_by(|(a, _), (b, _)| self.compare(a, b).then_with(|| a.name.cmp(&b.name)));
+
+        *items = keyed.into_iter().map(|(_, item)| item).collect();
+    }
+
+    fn compare(&self, a: &SortKey, b: &SortKey) -> Ordering {
+        match self {
+            Self::Score | Self::Alphabetical => Ordering::Equal,
+            Self::Frecency => b.frecency.total_cmp(&a.frecency),
+            Self::Size => (b.tabs, b.panes).cmp(&(a.tabs, a.panes)),
+            Self::Activity => b.last_changed.cmp(&a.last_changed),
+            Self::Attached => (b.clients > 0).cmp(&(a.clients > 0)),
+        }
+    }
+}
+
+/// Values of an item, that the sort orders compare.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SortKey {
+    pub name: String,
+    pub frecency: f64,
+    pub tabs: usize,
+    pub panes: usize,
+    pub last_changed: u64,
+    pub clients: usize,
+}
+
+impl SortKey {
+    /// Key with the values of a running session.
+    pub fn session(
+        name: &str,
+        session: Option<&SessionInfo>,
+        metadata: Option<&SessionMetadata>,
+        frecency: f64,
+    ) -> Self {
+        Self {
+            name: name.to_owned(),
+            frecency,
+            tabs: session.map(|s| s.tabs.len()).unwrap_or_default(),
+            panes: session.map(format::pane_count).unwrap_or_default(),
+            last_changed: metadata.map(|m| m.last_changed).unwrap_or_default(),
+            clients: session.map(|s| s.connected_clients).unwrap_or_default(),
+        }
+    }
+}
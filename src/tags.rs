@@ -0,0 +1,135 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+const CACHE_PATH: &str = "/cache/tags";
+
+/// Tags of sessions, persisted in the plugin cache as lines of
+/// `session<TAB>tag1,tag2`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tags {
+    by_session: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Tags {
+    pub fn load() -> Self {
+        let by_session = std::fs::read_to_string(CACHE_PATH)
+            .unwrap_or_default()
+            .lines()
+            .flat_map(|line| {
+                let (session, tags) = line.split_once('\t')?;
+
+                Some((session.to_owned(), parse(tags)))
+            })
+            .filter(|(_, tags)| !tags.is_empty())
+            .collect();
+
+        Self { by_session }
+    }
+
+    pub fn save(&self) {
+        let content = self
+            .by_session
+            .iter()
+            .map(|(session, tags)| {
+                format!(
+                    "{}\t{}",
+                    session,
+                    tags.iter().cloned().collect::<Vec<String>>().join(",")
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let _ = std::fs::write(CACHE_PATH, content);
+    }
+
+    pub fn get(&self, session: &str) -> Option<&BTreeSet<String>> {
+        self.by_session.get(session)
+    }
+
+    /// Replace the tags of a session. An empty set removes the session.
+    pub fn set(&mut self, session: &str, tags: BTreeSet<String>) {
+        if tags.is_empty() {
+            self.by_session.remove(session);
+            return;
+        }
+
+        self.by_session.insert(session.to_owned(), tags);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_session.is_empty()
+    }
+
+    /// Tag, that a session is grouped under.
+    pub fn group(&self, session: &str) -> Option<&String> {
+        self.get(session).and_then(|tags| tags.iter().next())
+    }
+
+    /// Check, if the session has a tag starting with every filter.
+    pub fn matches(&self, session: &str, filters: &[String]) -> bool {
+        filters.iter().all(|filter| {
+            self.get(session)
+                .map(|tags| tags.iter().any(|tag| tag.starts_with(filter.as_str())))
+                .unwrap_or(false)
+        })
+    }
+}
+
+/// Parse tags separated by commas or whitespace. A leading `#` is optional.
+pub fn parse(input: &str) -> BTreeSet<String> {
+    input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .map(|tag| tag.trim_start_matches('#'))
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| tag.to_owned())
+        .collect()
+}
+
+/// Split `#tag` filters from the search query. The remaining query keeps
+/// its space separated levels.
+pub fn split_query(query: &str) -> (String, Vec<String>) {
+    let mut filters = vec![];
+    let mut parts = vec![];
+
+    for part in query.split(' ') {
+        match part.strip_prefix('#') {
+            Some(filter) => filters.push(filter.to_owned()),
+            None => parts.push(part),
+        }
+    }
+
+    (parts.join(" "), filters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> BTreeSet<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_tags() {
+        assert_eq!(
+            parse("work, #backend  rust"),
+            tags(&["backend", "rust", "work"])
+        );
+        assert_eq!(parse("work,work"), tags(&["work"]));
+        assert_eq!(parse(" , # ,, "), tags(&[]));
+    }
+
+    #[test]
+    fn split_filters() {
+        assert_eq!(
+            split_query("api #work logs #rust"),
+            (
+                "api logs".to_owned(),
+                vec!["work".to_owned(), "rust".to_owned()]
+            )
+        );
+        assert_eq!(split_query("api  logs"), ("api  logs".to_owned(), vec![]));
+        assert_eq!(split_query("#"), ("".to_owned(), vec!["".to_owned()]));
+        assert_eq!(split_query(""), ("".to_owned(), vec![]));
+    }
+}
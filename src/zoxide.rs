@@ -0,0 +1,59 @@
+use zellij_tile::shim::run_command;
+
+use crate::command;
+
+pub fn query() {
+    let context = command::context("list", &[]);
+
+    run_command(&["zoxide", "query", "--list", "--score"], context);
+}
+
+pub fn add(path: &str) {
+    let context = command::context("zoxide_add", &[]);
+
+    run_command(&["zoxide", "add", path], context);
+}
+
+/// Parse the output of `zoxide query -ls` into (path, score) pairs. Lines
+/// look like `  12.5 /home/user/src/api`. Malformed lines are skipped.
+pub fn parse_scores(output: &str) -> Vec<(String, f64)> {
+    output
+        .lines()
+        .flat_map(|line| {
+            let (score, path) = line.trim().split_once(' ')?;
+            let score = score.parse::<f64>().ok()?;
+            let path = path.trim();
+
+            if path.is_empty() {
+                return None;
+            }
+
+            Some((path.to_owned(), score))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores() {
+        let output = "  12.5 /home/user/src/api\n   4 /home/user/my notes\n";
+
+        assert_eq!(
+            parse_scores(output),
+            vec![
+                ("/home/user/src/api".to_owned(), 12.5),
+                ("/home/user/my notes".to_owned(), 4.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped() {
+        let output = "\n/home/user/no-score\nabc /home/user/bad\n  1.0 \n  2.0 /ok";
+
+        assert_eq!(parse_scores(output), vec![("/ok".to_owned(), 2.0)]);
+    }
+}